@@ -20,7 +20,7 @@ fn bench_encrypt_string(c: &mut Criterion) {
     let message = String::from("hello");
 
     c.bench_function("encrypt_string", |b| {
-        b.iter(|| encrypt_string(&pk_string, &message, &params, None))
+        b.iter(|| encrypt_string(pk_string, &message, &params, None).unwrap())
     });
 }
 
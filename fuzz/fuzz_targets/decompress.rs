@@ -0,0 +1,13 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use module_lwe::utils::{compress, decompress};
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(s) = std::str::from_utf8(data) {
+        // anything that decodes must re-encode to the same coefficients
+        if let Ok(coeffs) = decompress(s) {
+            assert_eq!(decompress(&compress(&coeffs)), Ok(coeffs));
+        }
+    }
+});
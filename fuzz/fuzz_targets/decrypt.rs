@@ -0,0 +1,43 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use module_lwe::decrypt::try_decrypt;
+use module_lwe::utils::Parameters;
+use polynomial_ring::Polynomial;
+
+/// Read polynomials of arbitrary count, length and coefficient size from the input
+fn read_polys(data: &mut &[u8], width: usize) -> Vec<Polynomial<i64>> {
+    let count = take(data, 1).first().map_or(0, |&b| b as usize % 5);
+    (0..count)
+        .map(|_| {
+            let len = take(data, 1).first().map_or(0, |&b| b as usize % 40);
+            let coeffs = (0..len)
+                .map(|_| {
+                    let mut bytes = [0u8; 8];
+                    let chunk = take(data, width);
+                    bytes[..chunk.len()].copy_from_slice(chunk);
+                    // sign-extend from the chosen width
+                    let shift = 64 - 8 * width as u32;
+                    (i64::from_le_bytes(bytes) << shift) >> shift
+                })
+                .collect();
+            Polynomial::new(coeffs)
+        })
+        .collect()
+}
+
+fn take<'a>(data: &mut &'a [u8], len: usize) -> &'a [u8] {
+    let (head, tail) = data.split_at(len.min(data.len()));
+    *data = tail;
+    head
+}
+
+fuzz_target!(|data: &[u8]| {
+    let params = Parameters::preset("toy").unwrap();
+    let mut data = data;
+    let width = [1, 2, 8][take(&mut data, 1).first().map_or(0, |&b| b as usize % 3)];
+    let sk = read_polys(&mut data, width);
+    let u = read_polys(&mut data, width);
+    let v = read_polys(&mut data, width).pop().unwrap_or_else(|| Polynomial::new(vec![]));
+    let _ = try_decrypt(&sk, &u, &v, &params);
+});
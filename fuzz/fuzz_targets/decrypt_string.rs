@@ -0,0 +1,13 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use module_lwe::decrypt::decrypt_string;
+use module_lwe::utils::Parameters;
+
+fuzz_target!(|data: &[u8]| {
+    let params = Parameters::preset("toy").unwrap();
+    // the secret key and ciphertext are separated by the first newline
+    let input = String::from_utf8_lossy(data);
+    let (sk_string, ct_string) = input.split_once('\n').unwrap_or((&input, ""));
+    let _ = decrypt_string(sk_string, ct_string, &params);
+});
@@ -0,0 +1,11 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use module_lwe::encrypt::encrypt_string;
+use module_lwe::utils::Parameters;
+
+fuzz_target!(|data: &[u8]| {
+    let params = Parameters::preset("toy").unwrap();
+    let pk_string = String::from_utf8_lossy(data);
+    let _ = encrypt_string(&pk_string, "fuzz", &params, Some(0));
+});
@@ -0,0 +1,21 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use module_lwe::types::{PublicKey, SecretKey, Ciphertext};
+
+fuzz_target!(|data: &[u8]| {
+    // anything that decodes must re-encode to the same bytes
+    if let Ok(pk) = PublicKey::from_der(data) {
+        assert_eq!(pk.to_der(), data);
+    }
+    if let Ok(sk) = SecretKey::from_der(data) {
+        assert_eq!(sk.to_der(), data);
+    }
+    if let Ok(ct) = Ciphertext::from_der(data) {
+        assert_eq!(ct.to_der(), data);
+    }
+    let pem = String::from_utf8_lossy(data);
+    let _ = PublicKey::from_pem(&pem);
+    let _ = SecretKey::from_pem(&pem);
+    let _ = Ciphertext::from_pem(&pem);
+});
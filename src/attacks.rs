@@ -0,0 +1,333 @@
+use std::time::{Duration, Instant};
+use polynomial_ring::Polynomial;
+use crate::keygen::keygen;
+use crate::utils::{Parameters, PolyVec, PolyMatrix};
+
+/// Integer lattice basis, one basis vector per row
+pub type Basis = Vec<Vec<i64>>;
+
+/// Lovász constant used by `lll` inside `bkz`
+const DELTA: f64 = 0.99;
+
+/// Gram-Schmidt data of a basis: coefficients mu[i][j] and squared norms of the orthogonalized vectors
+struct GramSchmidt {
+    mu: Vec<Vec<f64>>,
+    b_star_sq: Vec<f64>,
+}
+
+fn dot(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b.iter()).map(|(x, y)| x * y).sum()
+}
+
+fn gram_schmidt(basis: &Basis) -> GramSchmidt {
+    let rows = basis.len();
+    let mut b_star: Vec<Vec<f64>> = Vec::with_capacity(rows);
+    let mut mu = vec![vec![0.0; rows]; rows];
+    let mut b_star_sq = vec![0.0; rows];
+    for i in 0..rows {
+        let b: Vec<f64> = basis[i].iter().map(|&x| x as f64).collect();
+        let mut v = b.clone();
+        for j in 0..i {
+            mu[i][j] = dot(&b, &b_star[j]) / b_star_sq[j];
+            for (vx, bx) in v.iter_mut().zip(b_star[j].iter()) {
+                *vx -= mu[i][j] * bx;
+            }
+        }
+        mu[i][i] = 1.0;
+        b_star_sq[i] = dot(&v, &v);
+        b_star.push(v);
+    }
+    GramSchmidt { mu, b_star_sq }
+}
+
+/// Squared Euclidean norm of an integer vector
+pub fn norm_sq(v: &[i64]) -> i64 {
+    v.iter().map(|x| x * x).sum()
+}
+
+/// LLL-reduce a full-rank integer basis in place
+/// # Arguments
+/// * `basis` - linearly independent integer vectors, one per row
+/// * `delta` - Lovász constant in (1/4, 1), typically 0.99
+/// # Example
+/// ```
+/// let mut basis = vec![vec![1, 0, 0], vec![4, 1, 0], vec![15, 7, 1]];
+/// module_lwe::attacks::lll(&mut basis, 0.99);
+/// assert!(basis.iter().all(|b| module_lwe::attacks::norm_sq(b) == 1));
+/// ```
+pub fn lll(basis: &mut Basis, delta: f64) {
+    let rows = basis.len();
+    if rows < 2 {
+        return;
+    }
+    let GramSchmidt { mut mu, mut b_star_sq } = gram_schmidt(basis);
+    let mut k = 1;
+    while k < rows {
+        // size-reduce b_k against b_{k-1}, ..., b_0
+        for j in (0..k).rev() {
+            let r = mu[k][j].round();
+            if r != 0.0 {
+                let ri = r as i64;
+                let (head, tail) = basis.split_at_mut(k);
+                for (x, y) in tail[0].iter_mut().zip(head[j].iter()) {
+                    *x -= ri * y;
+                }
+                let (head, tail) = mu.split_at_mut(k);
+                for (x, y) in tail[0][..=j].iter_mut().zip(head[j][..=j].iter()) {
+                    *x -= r * y;
+                }
+            }
+        }
+        let m = mu[k][k - 1];
+        if b_star_sq[k] >= (delta - m * m) * b_star_sq[k - 1] {
+            k += 1;
+            continue;
+        }
+        // swap b_k and b_{k-1} and update the Gram-Schmidt data
+        basis.swap(k, k - 1);
+        let b = b_star_sq[k] + m * m * b_star_sq[k - 1];
+        mu[k][k - 1] = m * b_star_sq[k - 1] / b;
+        b_star_sq[k] = b_star_sq[k - 1] * b_star_sq[k] / b;
+        b_star_sq[k - 1] = b;
+        let (head, tail) = mu.split_at_mut(k);
+        head[k - 1][..k - 1].swap_with_slice(&mut tail[0][..k - 1]);
+        for i in k + 1..rows {
+            let t = mu[i][k];
+            mu[i][k] = mu[i][k - 1] - m * t;
+            mu[i][k - 1] = t + mu[k][k - 1] * mu[i][k];
+        }
+        k = (k - 1).max(1);
+    }
+}
+
+/// State of the enumeration of a projected block
+struct Enumeration<'a> {
+    gs: &'a GramSchmidt,
+    start: usize,
+    x: Vec<i64>,
+    radius_sq: f64,
+    best: Option<Vec<i64>>,
+}
+
+impl Enumeration<'_> {
+    /// Try every coefficient of level i, closest to its center first, that keeps the projection within the radius
+    fn search(&mut self, i: usize, partial: f64) {
+        let (gs, start) = (self.gs, self.start);
+        let center = -(i + 1..self.x.len()).map(|j| self.x[j] as f64 * gs.mu[start + j][start + i]).sum::<f64>();
+        let rounded = center.round() as i64;
+        let side = if center >= rounded as f64 { 1 } else { -1 };
+        // rounded, rounded + side, rounded - side, rounded + 2 side, ... are in order of distance to the center
+        for offset in 0.. {
+            let x = rounded + side * if offset % 2 == 1 { (offset + 1) / 2 } else { -(offset / 2) };
+            let diff = x as f64 - center;
+            let length = partial + diff * diff * gs.b_star_sq[start + i];
+            if length >= self.radius_sq {
+                break;
+            }
+            self.x[i] = x;
+            if i > 0 {
+                self.search(i - 1, length);
+            } else if self.x.iter().any(|&c| c != 0) {
+                self.radius_sq = length;
+                self.best = Some(self.x.clone());
+            }
+        }
+        self.x[i] = 0;
+    }
+}
+
+/// Schnorr-Euchner enumeration of the shortest nonzero vector of the projected block [start, end)
+/// Returns the integer coefficients of a vector whose projection is shorter than `radius_sq`, if one exists
+fn enumerate(gs: &GramSchmidt, start: usize, end: usize, radius_sq: f64) -> Option<Vec<i64>> {
+    let mut state = Enumeration { gs, start, x: vec![0; end - start], radius_sq, best: None };
+    state.search(end - start - 1, 0.0);
+    state.best
+}
+
+/// Extended Euclid: (g, u, v) with u*a + v*b = g = gcd(a, b) >= 0
+fn extended_gcd(a: i64, b: i64) -> (i64, i64, i64) {
+    if b == 0 {
+        return if a < 0 { (-a, -1, 0) } else { (a, 1, 0) };
+    }
+    let (g, u, v) = extended_gcd(b, a % b);
+    (g, v, u - (a / b) * v)
+}
+
+/// Replace b_start by sum_i x_i b_{start+i} using unimodular operations, so the basis stays a basis
+fn insert(basis: &mut Basis, start: usize, x: &[i64]) {
+    let mut x = x.to_vec();
+    for i in (0..x.len() - 1).rev() {
+        let (a, b) = (x[i], x[i + 1]);
+        if b == 0 {
+            continue;
+        }
+        // [c1; c2] = [[a/g, b/g], [-v, u]] [b_i; b_{i+1}] has determinant 1 and a*b_i + b*b_{i+1} = g*c1
+        let (g, u, v) = extended_gcd(a, b);
+        let (bi, bj) = (basis[start + i].clone(), basis[start + i + 1].clone());
+        basis[start + i] = bi.iter().zip(bj.iter()).map(|(p, r)| (a / g) * p + (b / g) * r).collect();
+        basis[start + i + 1] = bi.iter().zip(bj.iter()).map(|(p, r)| -v * p + u * r).collect();
+        x[i] = g;
+        x[i + 1] = 0;
+    }
+    // the coefficients of a shortest vector are coprime, so x_0 = 1 here
+    if x[0] < 0 {
+        basis[start] = basis[start].iter().map(|c| -c).collect();
+    }
+}
+
+/// BKZ-reduce an integer basis in place
+/// Each tour enumerates the shortest vector of every projected block of `block_size` vectors and inserts it
+/// # Arguments
+/// * `basis` - linearly independent integer vectors, one per row
+/// * `block_size` - BKZ block size; 2 is LLL and the full dimension is HKZ
+/// * `max_tours` - stop after this many tours even if the basis is still changing
+pub fn bkz(basis: &mut Basis, block_size: usize, max_tours: usize) {
+    lll(basis, DELTA);
+    let rows = basis.len();
+    for _ in 0..max_tours {
+        let mut changed = false;
+        for start in 0..rows.saturating_sub(1) {
+            let end = (start + block_size).min(rows);
+            let gs = gram_schmidt(basis);
+            let radius_sq = DELTA * gs.b_star_sq[start];
+            if let Some(x) = enumerate(&gs, start, end, radius_sq) {
+                insert(basis, start, &x);
+                lll(basis, DELTA);
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+}
+
+/// Negacyclic rotation matrix of a polynomial: column j holds the coefficients of a * x^j mod x^n + 1
+pub fn rotation_matrix(a: &Polynomial<i64>, n: usize) -> Vec<Vec<i64>> {
+    let mut coeffs = a.coeffs().to_vec();
+    coeffs.resize(n, 0);
+    (0..n)
+        .map(|i| {
+            (0..n)
+                .map(|j| if i >= j { coeffs[i - j] } else { -coeffs[n + i - j] })
+                .collect()
+        })
+        .collect()
+}
+
+/// Expand a module-LWE public key (a, t = a*s + e) into a plain LWE instance (A, t) of dimension k*n
+/// # Returns
+/// * `(a_plain, t_plain)` - kn x kn integer matrix and kn target vector with t = A s + e mod q
+pub fn to_plain_lwe(a: &[Vec<Polynomial<i64>>], t: &[Polynomial<i64>], params: &Parameters) -> (Vec<Vec<i64>>, Vec<i64>) {
+    let (n, k) = (params.n, params.k);
+    let mut a_plain = vec![vec![0i64; k * n]; k * n];
+    for (i, row) in a.iter().enumerate() {
+        for (j, poly) in row.iter().enumerate() {
+            for (r, rot_row) in rotation_matrix(poly, n).into_iter().enumerate() {
+                a_plain[i * n + r][j * n..(j + 1) * n].copy_from_slice(&rot_row);
+            }
+        }
+    }
+    let t_plain = t.iter()
+        .flat_map(|p| {
+            let mut c = p.coeffs().to_vec();
+            c.resize(n, 0);
+            c
+        })
+        .collect();
+    (a_plain, t_plain)
+}
+
+/// Kannan embedding of an LWE instance t = A s + e mod q
+/// The rows span a lattice containing the short vector (e, -s, 1):
+/// `[q I_m, 0, 0]`, one row `[A column j, e_j, 0]` per secret coordinate, and `[t, 0, 1]`
+pub fn primal_embedding(a_plain: &[Vec<i64>], t_plain: &[i64], q: i64) -> Basis {
+    let (m, d) = (a_plain.len(), a_plain.first().map_or(0, |row| row.len()));
+    let dim = m + d + 1;
+    let mut basis = vec![vec![0i64; dim]; dim];
+    for (i, row) in basis.iter_mut().take(m).enumerate() {
+        row[i] = q;
+    }
+    for j in 0..d {
+        let row = &mut basis[m + j];
+        for i in 0..m {
+            row[i] = a_plain[i][j];
+        }
+        row[m + j] = 1;
+    }
+    basis[m + d][..m].copy_from_slice(t_plain);
+    basis[m + d][m + d] = 1;
+    basis
+}
+
+/// Check a candidate secret against the public key: t - a*s must be a ternary error
+fn is_secret(a_plain: &[Vec<i64>], t_plain: &[i64], s: &[i64], q: i64) -> bool {
+    a_plain.iter().zip(t_plain.iter()).all(|(row, t)| {
+        let e = (t - row.iter().zip(s.iter()).map(|(x, y)| x * y).sum::<i64>()).rem_euclid(q);
+        e <= 1 || e == q - 1
+    })
+}
+
+/// Recover the secret key behind a module-LWE public key with the primal attack
+/// # Arguments
+/// * `pk` - public key (a, t) from `keygen`
+/// * `params` - Parameters for the module-LWE cryptosystem
+/// * `block_size` - BKZ block size
+/// # Returns
+/// * `sk` - the secret key if the reduced basis exposes it
+/// # Example
+/// ```
+/// let params = module_lwe::utils::Parameters::builder().n(8).q(97).k(1).build().unwrap();
+/// let (pk, sk) = module_lwe::keygen::keygen(&params, None);
+/// let recovered = module_lwe::attacks::primal_attack(&pk, &params, 10).unwrap();
+/// assert!(recovered.iter().zip(sk.iter()).all(|(a, b)| a == b));
+/// ```
+pub fn primal_attack(pk: &(PolyMatrix, PolyVec), params: &Parameters, block_size: usize) -> Option<PolyVec> {
+    let (n, q) = (params.n, params.q);
+    let (a_plain, t_plain) = to_plain_lwe(&pk.0, &pk.1, params);
+    let mut basis = primal_embedding(&a_plain, &t_plain, q);
+    bkz(&mut basis, block_size, 8);
+    let (m, d) = (a_plain.len(), a_plain[0].len());
+    basis.iter().find_map(|row| {
+        let sign = row[m + d];
+        if sign.abs() != 1 {
+            return None;
+        }
+        let s: Vec<i64> = row[m..m + d].iter().map(|c| -c * sign).collect();
+        is_secret(&a_plain, &t_plain, &s, q).then(|| s.chunks(n).map(|c| Polynomial::new(c.to_vec())).collect())
+    })
+}
+
+/// Timing of the primal attack for one module rank
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttackTiming {
+    /// Module rank
+    pub k: usize,
+    /// Dimension of the embedding lattice, 2kn + 1
+    pub dimension: usize,
+    /// Time spent in key generation, embedding and reduction
+    pub elapsed: Duration,
+    /// Whether the recovered key equals the real one
+    pub recovered: bool,
+}
+
+/// Run the primal attack on fresh keys for each module rank and time it
+/// # Arguments
+/// * `n` - polynomial degree
+/// * `q` - ciphertext modulus
+/// * `ranks` - module ranks k to attack
+/// * `block_size` - BKZ block size
+/// # Returns
+/// * `timings` - one entry per rank, or an error if some (n, q, k) is not a valid parameter set
+pub fn scaling_report(n: usize, q: i64, ranks: &[usize], block_size: usize) -> Result<Vec<AttackTiming>, crate::error::Error> {
+    ranks.iter()
+        .map(|&k| {
+            let params = Parameters::builder().n(n).q(q).k(k).build()?;
+            let start = Instant::now();
+            let (pk, sk) = keygen(&params, None);
+            let recovered = primal_attack(&pk, &params, block_size)
+                .is_some_and(|s| s.iter().zip(sk.iter()).all(|(a, b)| a == b));
+            Ok(AttackTiming { k, dimension: 2 * k * n + 1, elapsed: start.elapsed(), recovered })
+        })
+        .collect()
+}
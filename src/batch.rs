@@ -0,0 +1,192 @@
+use polynomial_ring::Polynomial;
+use ring_lwe::utils::{polyadd, polysub, mod_coeffs};
+use ntt::{mod_exp, primitive_root};
+use crate::utils::{Parameters, PolyMatrix, PolyVec, add_vec, mul_vec_simple, mul_mat_vec_simple, transpose, scalar_mul, gen_small_vector, is_prime, SeedStream};
+use crate::error::Error;
+
+/// Encoder between n plaintext slots and plaintext polynomials in Z_t[X]/(X^n+1)
+/// For a prime t = 1 mod 2n, X^n + 1 splits into n linear factors X - psi^e over Z_t, where psi is a primitive
+/// 2n-th root of unity and e runs over the odd residues mod 2n. By the CRT a polynomial is determined by its values
+/// at these roots, so its coefficients can carry n independent slots that add and multiply element-wise.
+/// Slot i < n/2 is the value at psi^(5^i) and slot n/2 + i the value at psi^(-5^i), so that X -> X^5 rotates
+/// both rows of n/2 slots and X -> X^(2n-1) swaps the rows.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatchEncoder {
+    /// Ring degree
+    n: usize,
+    /// Plaintext modulus
+    t: i64,
+    /// Exponent e of the root psi^e of every slot
+    exponents: Vec<usize>,
+    /// Powers psi^0, ..., psi^(2n-1)
+    powers: Vec<i64>,
+}
+
+impl BatchEncoder {
+    /// Create an encoder for plaintext modulus t
+    /// # Arguments
+    /// * `params` - Parameters for the module-LWE cryptosystem
+    /// * `t` - plaintext modulus
+    /// # Returns
+    /// * `encoder` - the encoder, or an error unless t is a prime below q with t = 1 mod 2n
+    /// # Example
+    /// ```
+    /// use module_lwe::batch::BatchEncoder;
+    /// let params = module_lwe::utils::Parameters::builder().n(16).q(1073707009).k(2).build().unwrap();
+    /// let encoder = BatchEncoder::new(&params, 97).unwrap();
+    /// let m = encoder.encode(&[3, 1, 4, 1, 5]).unwrap();
+    /// assert_eq!(encoder.decode(&m)[..5], [3, 1, 4, 1, 5]);
+    /// assert!(BatchEncoder::new(&params, 101).is_err());
+    /// ```
+    pub fn new(params: &Parameters, t: u64) -> Result<Self, Error> {
+        let n = params.n;
+        let t = i64::try_from(t).unwrap_or(i64::MAX);
+        if n < 2 || t >= params.q || !is_prime(t) || t % (2 * n as i64) != 1 {
+            return Err(Error::InvalidParameters(format!("batching needs a prime t < q with t = 1 mod 2n = {}, got t = {}", 2 * n, t)));
+        }
+        let psi = mod_exp(primitive_root(t, 1), (t - 1) / (2 * n as i64), t);
+        let mut powers = vec![1i64; 2 * n];
+        for i in 1..2 * n {
+            powers[i] = mul_mod(powers[i - 1], psi, t);
+        }
+        let mut exponents = vec![0; n];
+        let mut e = 1;
+        for i in 0..n / 2 {
+            exponents[i] = e;
+            exponents[n / 2 + i] = 2 * n - e;
+            e = e * 5 % (2 * n);
+        }
+        Ok(BatchEncoder { n, t, exponents, powers })
+    }
+
+    /// Number of slots, equal to the ring degree n
+    pub fn slots(&self) -> usize {
+        self.n
+    }
+
+    /// Plaintext modulus t
+    pub fn plaintext_modulus(&self) -> u64 {
+        self.t as u64
+    }
+
+    /// Encode slot values into a plaintext polynomial
+    /// # Arguments
+    /// * `slots` - up to n values in [0, t), missing slots are zero
+    /// # Returns
+    /// * `m` - n coefficients in [0, t) whose evaluations are the slots, or an error if a value or the length is out of range
+    pub fn encode(&self, slots: &[u64]) -> Result<Vec<i64>, Error> {
+        let (n, t) = (self.n, self.t);
+        if slots.len() > n {
+            return Err(Error::InvalidParameters(format!("{} slot values do not fit in {} slots", slots.len(), n)));
+        }
+        if let Some(s) = slots.iter().find(|&&s| s >= t as u64) {
+            return Err(Error::InvalidParameters(format!("slot value {} is out of range for t = {}", s, t)));
+        }
+        // inverse transform: m_j = n^{-1} * sum_i s_i * psi^(-e_i*j)
+        let n_inv = mod_exp(n as i64, t - 2, t);
+        let coeffs = (0..n)
+            .map(|j| {
+                let sum = slots.iter().zip(self.exponents.iter()).fold(0i64, |acc, (&s, &e)| {
+                    let power = self.powers[(2 * n - e * j % (2 * n)) % (2 * n)];
+                    (acc + mul_mod(s as i64, power, t)) % t
+                });
+                mul_mod(sum, n_inv, t)
+            })
+            .collect();
+        Ok(coeffs)
+    }
+
+    /// Decode a plaintext polynomial into its slot values
+    /// # Arguments
+    /// * `m` - up to n coefficients, e.g. from `batch::decrypt`
+    /// # Returns
+    /// * `slots` - the n evaluations of m at the slot roots
+    pub fn decode(&self, m: &[i64]) -> Vec<u64> {
+        let (n, t) = (self.n, self.t);
+        self.exponents.iter()
+            .map(|&e| {
+                m.iter().take(n).enumerate().fold(0i64, |acc, (j, &c)| {
+                    (acc + mul_mod(c.rem_euclid(t), self.powers[e * j % (2 * n)], t)) % t
+                }) as u64
+            })
+            .collect()
+    }
+}
+
+fn mul_mod(a: i64, b: i64, m: i64) -> i64 {
+    (a as i128 * b as i128 % m as i128) as i64
+}
+
+/// Encrypt a plaintext polynomial with plaintext modulus t
+/// Generalizes `encrypt::encrypt` from half_q to delta = floor(q/t): v = t_pk*r + e2 - delta*m
+/// # Arguments
+/// * `pk` - public key (a, t_pk)
+/// * `m` - up to n coefficients in [0, t), e.g. from `BatchEncoder::encode`
+/// * `t` - plaintext modulus
+/// * `params` - Parameters for the module-LWE cryptosystem
+/// * `seed` - random seed
+/// # Returns
+/// * `(u, v)` - ciphertext, or an error unless 2 <= t < q and m has at most n coefficients
+/// # Example
+/// ```
+/// use module_lwe::batch::{BatchEncoder, encrypt, decrypt};
+/// use module_lwe::homomorphic::{add, mul_with_modulus};
+/// let params = module_lwe::utils::Parameters::builder().n(16).q(1073707009).k(2).build().unwrap();
+/// let (pk, sk) = module_lwe::keygen::keygen(&params, None);
+/// let rlk = module_lwe::keygen::relin_keygen(&sk, 1 << 10, &params, None);
+/// let encoder = BatchEncoder::new(&params, 97).unwrap();
+/// let ct0 = encrypt(&pk, &encoder.encode(&[2, 3, 4]).unwrap(), 97, &params, None).unwrap();
+/// let ct1 = encrypt(&pk, &encoder.encode(&[5, 6, 7]).unwrap(), 97, &params, None).unwrap();
+/// let sum = decrypt(&sk, &add(&ct0, &ct1, &params), 97, &params);
+/// assert_eq!(encoder.decode(&sum)[..3], [7, 9, 11]);
+/// let product = decrypt(&sk, &mul_with_modulus(&ct0, &ct1, 97, &rlk, &params), 97, &params);
+/// assert_eq!(encoder.decode(&product)[..3], [10, 18, 28]);
+/// ```
+pub fn encrypt(
+    pk: &(PolyMatrix, PolyVec),
+    m: &[i64],
+    t: i64,
+    params: &Parameters,
+    seed: Option<u64>
+) -> Result<(PolyVec, Polynomial<i64>), Error> {
+    let (n, q, k, f, omega) = (params.n, params.q, params.k, &params.f, params.omega);
+    if t < 2 || t >= q {
+        return Err(Error::InvalidParameters(format!("plaintext modulus {} is out of range for q = {}", t, q)));
+    }
+    if m.len() > n {
+        return Err(Error::InvalidParameters(format!("plaintext has {} coefficients, expected at most {}", m.len(), n)));
+    }
+    let mut seeds = SeedStream::new(seed);
+    let r = gen_small_vector(n, k, seeds.next_seed());
+    let e1 = gen_small_vector(n, k, seeds.next_seed());
+    let e2 = gen_small_vector(n, 1, seeds.next_seed()).remove(0);
+    let m: Vec<i64> = m.iter().map(|c| c.rem_euclid(t)).collect();
+    let scaled = scalar_mul(&Polynomial::new(m), q / t, q);
+    let u = add_vec(&mul_mat_vec_simple(&transpose(&pk.0), &r, q, f, omega), &e1, q, f);
+    let v = polysub(&polyadd(&mul_vec_simple(&pk.1, &r, q, f, omega), &e2, q, f), &scaled, q, f);
+    Ok((u, v))
+}
+
+/// Decrypt a ciphertext with plaintext modulus t
+/// # Arguments
+/// * `sk` - secret key
+/// * `ct` - ciphertext (u, v) from `encrypt` or the homomorphic operations
+/// * `t` - plaintext modulus
+/// * `params` - Parameters for the module-LWE cryptosystem
+/// # Returns
+/// * `m` - n coefficients in [0, t), rounding -t*(v - sk*u)/q
+pub fn decrypt(sk: &[Polynomial<i64>], ct: &(PolyVec, Polynomial<i64>), t: i64, params: &Parameters) -> Vec<i64> {
+    let (n, q, f, omega) = (params.n, params.q, &params.f, params.omega);
+    let phase = mod_coeffs(polysub(&ct.1, &mul_vec_simple(sk, &ct.0, q, f, omega), q, f), q);
+    let (t, q) = (t as i128, q as i128);
+    let mut m: Vec<i64> = phase.coeffs()
+        .iter()
+        .map(|&c| {
+            let num = -(c as i128) * t;
+            let r = if num >= 0 { (num + q / 2) / q } else { -((-num + q / 2) / q) };
+            r.rem_euclid(t) as i64
+        })
+        .collect();
+    m.resize(n, 0);
+    m
+}
@@ -0,0 +1,87 @@
+use polynomial_ring::Polynomial;
+use ring_lwe::utils::nearest_int;
+use crate::utils::{Parameters, PolyVec};
+
+/// Secret key recovered by `recover_secret_key`, with the number of oracle queries it took
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyRecovery {
+    /// Recovered secret key
+    pub sk: PolyVec,
+    /// Number of ciphertexts submitted to the decryption oracle
+    pub queries: usize,
+}
+
+/// Recover the secret key from a decryption oracle with crafted ciphertexts
+///
+/// `decrypt` computes `v - sk*u` and rounds every coefficient to 0 or half_q. Submitting `u = e_j` (the constant 1
+/// in slot j) and `v = w` in every coefficient makes coefficient i of the phase `w - sk_j[i]`, so the decrypted bit
+/// flips exactly when w crosses the rounding threshold shifted by `sk_j[i]`. The threshold itself is found first
+/// by binary search with `u = 0`, then 2*bound queries per slot of `sk` read off all n coefficients at once.
+/// None of these ciphertexts could come out of `encrypt`, which is why a scheme exposed to chosen ciphertexts needs
+/// a CCA transform that rejects them.
+/// # Arguments
+/// * `params` - Parameters for the module-LWE cryptosystem
+/// * `bound` - largest magnitude of a secret coefficient, 1 for the ternary secrets of `keygen`
+/// * `oracle` - decrypts `(u, v)` to a binary vector, or returns `None` if it rejects the ciphertext
+/// # Returns
+/// * `recovery` - the secret key and the number of queries, or `None` if the oracle rejected a query
+///   or answered inconsistently with `decrypt`
+/// # Example
+/// ```
+/// let params = module_lwe::utils::Parameters::default();
+/// let (_, sk) = module_lwe::keygen::keygen(&params, None);
+/// let oracle = |u: &[polynomial_ring::Polynomial<i64>], v: &polynomial_ring::Polynomial<i64>| {
+///     Some(module_lwe::decrypt::decrypt(&sk, u, v, &params))
+/// };
+/// let recovery = module_lwe::cca::recover_secret_key(&params, 1, oracle).unwrap();
+/// assert_eq!(recovery.sk, sk);
+/// ```
+pub fn recover_secret_key<O>(params: &Parameters, bound: i64, mut oracle: O) -> Option<KeyRecovery>
+where
+    O: FnMut(&[Polynomial<i64>], &Polynomial<i64>) -> Option<Vec<i64>>,
+{
+    let (n, q, k) = (params.n, params.q, params.k);
+    let half_q = nearest_int(q, 2);
+    let mut queries = 0;
+    let mut query = |u: &[Polynomial<i64>], w: i64| -> Option<Vec<i64>> {
+        queries += 1;
+        let mut bits = oracle(u, &Polynomial::new(vec![w; n]))?;
+        bits.resize(n, 0);
+        Some(bits)
+    };
+
+    // smallest w whose phase decrypts to 1, assuming the bit is monotone in w on [0, half_q]
+    let zero = vec![Polynomial::new(vec![]); k];
+    let (mut low, mut high) = (0, half_q);
+    if query(&zero, low)?[0] != 0 || query(&zero, high)?[0] != 1 {
+        return None;
+    }
+    while high - low > 1 {
+        let mid = (low + high) / 2;
+        if query(&zero, mid)?[0] == 1 {
+            high = mid;
+        } else {
+            low = mid;
+        }
+    }
+    let threshold = high;
+    if threshold <= bound || threshold + bound >= half_q {
+        return None;
+    }
+
+    // coefficient i of slot j decrypts to 1 exactly when w >= threshold + sk_j[i]
+    let mut sk = Vec::with_capacity(k);
+    for j in 0..k {
+        let mut u = zero.clone();
+        u[j] = Polynomial::new(vec![1]);
+        let mut ones = vec![0i64; n];
+        for w in threshold - bound..threshold + bound {
+            for (count, bit) in ones.iter_mut().zip(query(&u, w)?) {
+                *count += bit;
+            }
+        }
+        // w = threshold + bound always decrypts to 1, so it is counted without a query
+        sk.push(Polynomial::new(ones.iter().map(|&c| bound - c).collect()));
+    }
+    Some(KeyRecovery { sk, queries })
+}
@@ -0,0 +1,183 @@
+use polynomial_ring::Polynomial;
+use sha3::Shake256;
+use sha3::digest::{Update, ExtendableOutput, XofReader};
+use crate::utils::{Parameters, PolyMatrix, PolyVec, gen_uniform_matrix, compress, decompress};
+use crate::keygen::keygen_with_matrix;
+use crate::encrypt::encrypt as encrypt_with_key;
+use crate::dkg::shared_matrix;
+use crate::types::{check_len, check_vec, to_canonical};
+use crate::error::Error;
+
+/// Length of a CRS identifier in bytes
+pub const CRS_ID_BYTES: usize = 32;
+
+/// Common reference string: one public matrix shared by every key pair of a deployment
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Crs {
+    /// Parameters the matrix was generated for
+    params: Parameters,
+    /// Uniform k x k public matrix
+    a: PolyMatrix,
+    /// SHAKE256 of the parameters and the matrix
+    id: [u8; CRS_ID_BYTES],
+}
+
+/// Public key that references a CRS instead of carrying the matrix
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CrsPublicKey {
+    /// Identifier of the CRS the key was generated against
+    pub crs_id: [u8; CRS_ID_BYTES],
+    /// t = a*sk + e
+    pub t: PolyVec,
+}
+
+impl Crs {
+    /// Sample a fresh CRS
+    /// # Arguments
+    /// * `params` - Parameters for the module-LWE cryptosystem
+    /// * `seed` - public seed, so that anyone can regenerate the matrix, or `None` for a random one
+    /// # Returns
+    /// * `crs` - the common reference string
+    pub fn generate(params: &Parameters, seed: Option<u64>) -> Self {
+        let a = match seed {
+            Some(s) => shared_matrix(params, s),
+            None => gen_uniform_matrix(params.n, params.k, params.q, None),
+        };
+        Crs::new(params, a)
+    }
+
+    /// Load a CRS from a matrix, e.g. the `a` of an existing public key
+    /// # Arguments
+    /// * `a` - public matrix
+    /// * `params` - Parameters for the module-LWE cryptosystem
+    /// # Returns
+    /// * `crs` - the common reference string, or an error if the matrix does not match the parameters
+    pub fn from_matrix(a: &[Vec<Polynomial<i64>>], params: &Parameters) -> Result<Self, Error> {
+        check_len("a", a.len(), params.k)?;
+        for row in a.iter() {
+            check_vec(row, params)?;
+        }
+        Ok(Crs::new(params, a.to_vec()))
+    }
+
+    /// Load a CRS from the base64 encoding of `to_base64`
+    /// # Arguments
+    /// * `crs_string` - matrix coefficients in base64 encoding
+    /// * `params` - Parameters for the module-LWE cryptosystem
+    /// # Returns
+    /// * `crs` - the common reference string, or an error if the string is malformed
+    /// # Example
+    /// ```
+    /// use module_lwe::crs::Crs;
+    /// let params = module_lwe::utils::Parameters::default();
+    /// let crs = Crs::generate(&params, Some(45));
+    /// let loaded = Crs::from_base64(&crs.to_base64(), &params).unwrap();
+    /// assert_eq!(loaded.id(), crs.id());
+    /// ```
+    pub fn from_base64(crs_string: &str, params: &Parameters) -> Result<Self, Error> {
+        let (n, k) = (params.n, params.k);
+        let coeffs = decompress(crs_string)?;
+        check_len("CRS", coeffs.len(), k * k * n)?;
+        let a: PolyMatrix = coeffs.chunks(k * n)
+            .map(|row| row.chunks(n).map(|c| Polynomial::new(c.to_vec())).collect())
+            .collect();
+        Crs::from_matrix(&a, params)
+    }
+
+    /// Encode the matrix coefficients in base64
+    pub fn to_base64(&self) -> String {
+        let coeffs: Vec<i64> = self.a.iter().flatten()
+            .flat_map(|p| {
+                let mut coeffs = p.coeffs().to_vec();
+                coeffs.resize(self.params.n, 0);
+                coeffs
+            })
+            .collect();
+        compress(&coeffs)
+    }
+
+    /// The shared public matrix
+    pub fn matrix(&self) -> &PolyMatrix {
+        &self.a
+    }
+
+    /// The parameters the CRS was generated for
+    pub fn params(&self) -> &Parameters {
+        &self.params
+    }
+
+    /// Identifier that public keys use to reference the CRS
+    pub fn id(&self) -> [u8; CRS_ID_BYTES] {
+        self.id
+    }
+
+    fn new(params: &Parameters, a: PolyMatrix) -> Self {
+        // hash canonical coefficients so that equal matrices get equal identifiers however they are represented
+        let mut hasher = Shake256::default();
+        for x in [params.n as u64, params.q as u64, params.k as u64] {
+            hasher.update(&x.to_le_bytes());
+        }
+        for c in a.iter().flatten().flat_map(|p| to_canonical(p, params)) {
+            hasher.update(&(c as u64).to_le_bytes());
+        }
+        let mut id = [0u8; CRS_ID_BYTES];
+        hasher.finalize_xof().read(&mut id);
+        Crs { params: params.clone(), a, id }
+    }
+}
+
+impl CrsPublicKey {
+    /// Expand the key into the (a, t) form of `keygen::keygen`
+    /// # Arguments
+    /// * `crs` - the CRS the key references
+    /// # Returns
+    /// * `(a, t)` - full public key, or an error if the key references another CRS
+    pub fn to_public_key(&self, crs: &Crs) -> Result<(PolyMatrix, PolyVec), Error> {
+        check_crs(self, crs)?;
+        Ok((crs.a.clone(), self.t.clone()))
+    }
+}
+
+fn check_crs(pk: &CrsPublicKey, crs: &Crs) -> Result<(), Error> {
+    if pk.crs_id != crs.id {
+        let hex = |id: &[u8]| id[..8].iter().map(|b| format!("{:02x}", b)).collect::<String>();
+        return Err(Error::InvalidParameters(format!("public key references CRS {}, not {}", hex(&pk.crs_id), hex(&crs.id))));
+    }
+    check_vec(&pk.t, &crs.params)
+}
+
+/// Generate a key pair against a CRS
+/// # Arguments
+/// * `crs` - the common reference string
+/// * `seed` - random seed
+/// # Returns
+/// * `(pk, sk)` - public key referencing the CRS and secret key
+pub fn keygen(crs: &Crs, seed: Option<u64>) -> (CrsPublicKey, PolyVec) {
+    let ((_, t), sk) = keygen_with_matrix(&crs.a, &crs.params, seed);
+    (CrsPublicKey { crs_id: crs.id, t }, sk)
+}
+
+/// Encrypt a message to a public key that references a CRS
+/// # Arguments
+/// * `crs` - the common reference string
+/// * `pk` - recipient's public key
+/// * `m_b` - binary message
+/// * `seed` - random seed
+/// # Returns
+/// * `(u, v)` - ciphertext for `decrypt::decrypt`, or an error if the key references another CRS
+/// # Example
+/// ```
+/// use module_lwe::crs::{Crs, keygen, encrypt};
+/// let params = module_lwe::utils::Parameters::default();
+/// let crs = Crs::generate(&params, Some(45));
+/// let (pk, sk) = keygen(&crs, None);
+/// let mut m_b = vec![1, 0, 1, 1];
+/// m_b.resize(params.n(), 0);
+/// let (u, v) = encrypt(&crs, &pk, &m_b, None).unwrap();
+/// assert_eq!(module_lwe::decrypt::decrypt(&sk, &u, &v, &params), m_b);
+/// assert!(encrypt(&Crs::generate(&params, Some(46)), &pk, &m_b, None).is_err());
+/// ```
+pub fn encrypt(crs: &Crs, pk: &CrsPublicKey, m_b: &[i64], seed: Option<u64>) -> Result<(PolyVec, Polynomial<i64>), Error> {
+    check_crs(pk, crs)?;
+    Ok(encrypt_with_key(&crs.a, &pk.t, m_b, &crs.params, seed))
+}
@@ -1,6 +1,8 @@
 use polynomial_ring::Polynomial;
 use ring_lwe::utils::{polysub,nearest_int};
 use crate::utils::{Parameters,mul_vec_simple,decompress};
+use crate::types::{check_len, check_vec, check_poly};
+use crate::error::Error;
 
 /// Decrypt a ciphertext
 /// # Arguments
@@ -16,19 +18,24 @@ use crate::utils::{Parameters,mul_vec_simple,decompress};
 /// let params = module_lwe::utils::Parameters::default();
 /// let (pk,sk) = module_lwe::keygen::keygen(&params, None);
 /// let mut m_b = vec![0,1,0,1,0,0,1,1,1,0,1];
-/// m_b.resize(params.n, 0);
+/// m_b.resize(params.n(), 0);
 /// let (u, v) = module_lwe::encrypt::encrypt(&pk.0, &pk.1, &m_b, &params, None);
 /// let decrypted_coeffs = module_lwe::decrypt::decrypt(&sk, &u, &v, &params);
 /// assert_eq!(m_b, decrypted_coeffs);
 /// ```
 pub fn decrypt(
-    sk: &Vec<Polynomial<i64>>,    //secret key
-    u: &Vec<Polynomial<i64>>, //ciphertext vector
+    sk: &[Polynomial<i64>],    //secret key
+    u: &[Polynomial<i64>], //ciphertext vector
 	v: &Polynomial<i64> ,		//ciphertext polynomial
     params: &Parameters
 ) -> Vec<i64> {
 	let (q, f, omega) = (params.q, &params.f, params.omega); //get parameters
-	let scaled_pt = polysub(&v, &mul_vec_simple(&sk, &u, q, &f, omega), q, f); //Compute v-sk*u mod q
+	let scaled_pt = polysub(v, &mul_vec_simple(sk, u, q, f, omega), q, f); //Compute v-sk*u mod q
+	decode(&scaled_pt, q)
+}
+
+/// Round every coefficient of the phase `v - sk*u` to the nearest multiple of half_q and read off the bit
+pub(crate) fn decode(scaled_pt: &Polynomial<i64>, q: i64) -> Vec<i64> {
 	let half_q = nearest_int(q,2); // compute nearest integer to q/2
 	let mut decrypted_coeffs = vec![];
 	let mut s;
@@ -39,19 +46,50 @@ pub fn decrypt(
     decrypted_coeffs
 }
 
+/// Decrypt a ciphertext of untrusted shape
+/// Unlike `decrypt`, which assumes well-formed inputs, this checks that `sk` and `u` have k polynomials
+/// and that every polynomial has at most n coefficients in (-q, q)
+/// # Arguments
+/// * `sk` - secret key
+/// * `u` - ciphertext vector
+/// * `v` - ciphertext polynomial
+/// * `params` - Parameters for the module-LWE cryptosystem
+/// # Returns
+/// * `decrypted_coeffs` - plaintext vector, or an error describing the malformed input
+/// # Example
+/// ```
+/// use polynomial_ring::Polynomial;
+/// let params = module_lwe::utils::Parameters::default();
+/// let (_, sk) = module_lwe::keygen::keygen(&params, None);
+/// let u = vec![Polynomial::new(vec![1]); params.k() + 1];
+/// assert!(module_lwe::decrypt::try_decrypt(&sk, &u, &Polynomial::new(vec![1]), &params).is_err());
+/// ```
+pub fn try_decrypt(
+    sk: &[Polynomial<i64>],
+    u: &[Polynomial<i64>],
+    v: &Polynomial<i64>,
+    params: &Parameters
+) -> Result<Vec<i64>, Error> {
+    check_vec(sk, params)?;
+    check_vec(u, params)?;
+    check_poly(v, params)?;
+    Ok(decrypt(sk, u, v, params))
+}
+
 /// decrypt a ciphertext string given a secret key
 /// # Arguments
 /// * `sk_string` - secret key string in base64 encoding
 /// * `ciphertext_string` - ciphertext string in base64 encoding
 /// * `params` - Parameters for the ring-LWE cryptosystem
 /// # Returns
-/// * `message_string` - decrypted message string as plaintext
-pub fn decrypt_string(sk_string: &String, ciphertext_base64: &String, params: &Parameters) -> String {
+/// * `message_string` - decrypted message string as plaintext, or an error if the key or ciphertext is malformed
+pub fn decrypt_string(sk_string: &str, ciphertext_base64: &str, params: &Parameters) -> Result<String, Error> {
     // Get parameters
     let (n, k) = (params.n, params.k);
 
     // Base64 decode the secret key string
-    let sk_array: Vec<i64> = decompress(sk_string);
+    let sk_array: Vec<i64> = decompress(sk_string)?;
+    check_len("secret key", sk_array.len(), k * n)?;
 
     // Convert the secret key into a Vec<Polynomial<i64>>
     let sk: Vec<Polynomial<i64>> = sk_array.chunks(n)
@@ -59,9 +97,12 @@ pub fn decrypt_string(sk_string: &String, ciphertext_base64: &String, params: &P
         .collect();
 
     // Base64 decode and deserialize the ciphertext string
-    let ciphertext_list: Vec<i64> = decompress(ciphertext_base64);
+    let ciphertext_list: Vec<i64> = decompress(ciphertext_base64)?;
 
     let block_size = (k + 1) * n;
+    if !ciphertext_list.len().is_multiple_of(block_size) {
+        return Err(Error::Decode(format!("ciphertext has {} coefficients, expected a multiple of {}", ciphertext_list.len(), block_size)));
+    }
     let num_blocks = ciphertext_list.len() / block_size;
 
     let mut message_binary = vec![];
@@ -77,7 +118,7 @@ pub fn decrypt_string(sk_string: &String, ciphertext_base64: &String, params: &P
         let v = Polynomial::new(v_array.to_vec());
         
         // Decrypt the ciphertext
-        let mut m_b = decrypt(&sk, &u, &v, &params);
+        let mut m_b = try_decrypt(&sk, &u, &v, params)?;
         m_b.resize(n, 0);
         
         message_binary.extend(m_b);
@@ -94,5 +135,5 @@ pub fn decrypt_string(sk_string: &String, ciphertext_base64: &String, params: &P
         .collect();
 
     // Trim the null characters \0 = '00000000' from the end
-    message_string.trim_end_matches('\0').to_string()
+    Ok(message_string.trim_end_matches('\0').to_string())
 }
@@ -0,0 +1,243 @@
+use polynomial_ring::Polynomial;
+use sha3::Shake256;
+use sha3::digest::{Update, ExtendableOutput, XofReader};
+use ring_lwe::utils::{polyadd, gen_uniform_poly, mod_coeffs};
+use crate::utils::{Parameters, PolyMatrix, PolyVec, add_vec, mul_vec_simple, mul_mat_vec_simple, gen_small_vector, gen_bounded_vector, SeedStream};
+use crate::threshold::PartialDecryption;
+use crate::types::{check_len, check_vec};
+use crate::error::Error;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// A party's contribution to the joint public key
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(from = "RawPublicShare", into = "RawPublicShare"))]
+pub struct PublicShare {
+    /// Index of the party, from 1 to N
+    pub index: i64,
+    /// t_i = a*sk_i + e_i
+    pub t: PolyVec,
+}
+
+impl PublicShare {
+    /// Commitment to this share, published before the share itself
+    pub fn commitment(&self) -> ShareCommitment {
+        let mut hasher = Shake256::default();
+        hasher.update(&self.index.to_le_bytes());
+        for p in self.t.iter() {
+            hasher.update(&(p.coeffs().len() as u64).to_le_bytes());
+            for c in p.coeffs() {
+                hasher.update(&c.to_le_bytes());
+            }
+        }
+        let mut digest = [0u8; 32];
+        hasher.finalize_xof().read(&mut digest);
+        ShareCommitment { index: self.index, digest }
+    }
+}
+
+/// A party's binding commitment to its public share
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ShareCommitment {
+    /// Index of the party, from 1 to N
+    pub index: i64,
+    /// SHAKE256 digest of the index and t_i
+    pub digest: [u8; 32],
+}
+
+/// Messages exchanged by the parties of the protocol
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Message {
+    /// Round one: a party commits to its share of the public key
+    Commitment(ShareCommitment),
+    /// Round two: once every commitment is in, a party reveals its share
+    PublicShare(PublicShare),
+    /// Decryption: a party publishes sk_i*u plus smudging noise
+    PartialDecryption(PartialDecryption),
+}
+
+/// One participant of the distributed key generation, holding its secret share
+#[derive(Debug, Clone)]
+pub struct Party {
+    /// Index of the party, from 1 to N
+    pub index: i64,
+    /// Shared public matrix
+    a: PolyMatrix,
+    /// Secret share sk_i, never sent
+    sk: PolyVec,
+    /// Public share t_i = a*sk_i + e_i
+    t: PolyVec,
+    /// Parameters for the module-LWE cryptosystem
+    params: Parameters,
+}
+
+/// Public matrix that every party derives from the same public seed, e.g. the output of a coin-flipping round
+/// # Arguments
+/// * `params` - Parameters for the module-LWE cryptosystem
+/// * `seed` - public seed agreed on by the parties
+/// # Returns
+/// * `a` - uniform k x k matrix
+pub fn shared_matrix(params: &Parameters, seed: u64) -> PolyMatrix {
+    let (n, q, k) = (params.n, params.q, params.k);
+    let mut seeds = SeedStream::new(Some(seed));
+    (0..k)
+        .map(|_| (0..k).map(|_| mod_coeffs(gen_uniform_poly(n, q, seeds.next_seed()), q)).collect())
+        .collect()
+}
+
+impl Party {
+    /// Sample a secret share sk_i and error e_i against the shared matrix
+    /// # Arguments
+    /// * `index` - index of the party, from 1 to N
+    /// * `a` - shared public matrix from `shared_matrix`
+    /// * `params` - Parameters for the module-LWE cryptosystem
+    /// * `seed` - random seed
+    /// # Returns
+    /// * `party` - the party, or an error if `a` does not match the parameters or the index is not positive
+    pub fn new(index: i64, a: &[Vec<Polynomial<i64>>], params: &Parameters, seed: Option<u64>) -> Result<Self, Error> {
+        let (n, q, k, f, omega) = (params.n, params.q, params.k, &params.f, params.omega);
+        if index < 1 {
+            return Err(Error::InvalidParameters(format!("party index {} is not positive", index)));
+        }
+        check_len("a", a.len(), k)?;
+        for row in a.iter() {
+            check_vec(row, params)?;
+        }
+        let mut seeds = SeedStream::new(seed);
+        let sk = gen_small_vector(n, k, seeds.next_seed());
+        let e = gen_small_vector(n, k, seeds.next_seed());
+        let t = add_vec(&mul_mat_vec_simple(a, &sk, q, f, omega), &e, q, f);
+        Ok(Party { index, a: a.to_vec(), sk, t, params: params.clone() })
+    }
+
+    /// The round-one message committing to this party's public share
+    pub fn commitment(&self) -> Message {
+        Message::Commitment(self.share().commitment())
+    }
+
+    /// The round-two message revealing this party's public share, to be sent only after every commitment is received
+    pub fn public_share(&self) -> Message {
+        Message::PublicShare(self.share())
+    }
+
+    fn share(&self) -> PublicShare {
+        PublicShare { index: self.index, t: self.t.clone() }
+    }
+
+    /// The shared public matrix this party generated its share against
+    pub fn matrix(&self) -> &PolyMatrix {
+        &self.a
+    }
+
+    /// Partially decrypt a ciphertext under the joint public key
+    /// # Arguments
+    /// * `u` - ciphertext vector
+    /// * `smudging` - bound on the smudging noise added to hide sk_i
+    /// * `seed` - random seed
+    /// # Returns
+    /// * `message` - the partial decryption sk_i*u + noise, for `threshold::combine`
+    pub fn partial_decrypt(&self, u: &[Polynomial<i64>], smudging: i64, seed: Option<u64>) -> Result<Message, Error> {
+        let params = &self.params;
+        let (n, q, f, omega) = (params.n, params.q, &params.f, params.omega);
+        check_vec(u, params)?;
+        let noise = gen_bounded_vector(n, 1, smudging.max(0), seed).remove(0);
+        let d = polyadd(&mul_vec_simple(&self.sk, u, q, f, omega), &noise, q, f);
+        Ok(Message::PartialDecryption(PartialDecryption { index: self.index, d }))
+    }
+}
+
+/// Sum the public shares of all parties into the joint public key
+/// The joint secret key is the sum of the secret shares, which no party knows, so decryption
+/// needs a partial decryption from every party.
+/// A party that saw the other shares first could publish t* - sum of theirs and so choose the whole key (a rogue-key
+/// attack). Every share must therefore match a commitment, and the commitments of all parties must be collected
+/// before any share is revealed.
+/// # Arguments
+/// * `a` - shared public matrix
+/// * `commitments` - round-one commitments of all N parties
+/// * `shares` - public shares of all N parties
+/// * `params` - Parameters for the module-LWE cryptosystem
+/// # Returns
+/// * `(a, t)` - public key for `encrypt::encrypt`, or an error if a share is malformed, an index repeats or a share
+///   does not match its commitment
+/// # Example
+/// ```
+/// use module_lwe::dkg::{shared_matrix, joint_public_key, Party, Message};
+/// use module_lwe::threshold::combine;
+/// let params = module_lwe::utils::Parameters::default();
+/// let a = shared_matrix(&params, 7);
+/// let parties: Vec<Party> = (1..=3).map(|i| Party::new(i, &a, &params, None).unwrap()).collect();
+/// let commitments: Vec<_> = parties.iter().map(|p| match p.commitment() {
+///     Message::Commitment(commitment) => commitment,
+///     _ => unreachable!(),
+/// }).collect();
+/// let shares: Vec<_> = parties.iter().map(|p| match p.public_share() {
+///     Message::PublicShare(share) => share,
+///     _ => unreachable!(),
+/// }).collect();
+/// let pk = joint_public_key(&a, &commitments, &shares, &params).unwrap();
+///
+/// let mut m_b = vec![1, 1, 0, 1];
+/// m_b.resize(params.n(), 0);
+/// let (u, v) = module_lwe::encrypt::encrypt(&pk.0, &pk.1, &m_b, &params, None);
+/// let partials: Vec<_> = parties.iter().map(|p| match p.partial_decrypt(&u, 100, None).unwrap() {
+///     Message::PartialDecryption(partial) => partial,
+///     _ => unreachable!(),
+/// }).collect();
+/// assert_eq!(combine(&partials, &v, &params).unwrap(), m_b);
+/// ```
+pub fn joint_public_key(
+    a: &[Vec<Polynomial<i64>>],
+    commitments: &[ShareCommitment],
+    shares: &[PublicShare],
+    params: &Parameters
+) -> Result<(PolyMatrix, PolyVec), Error> {
+    let (q, k, f) = (params.q, params.k, &params.f);
+    if shares.is_empty() {
+        return Err(Error::InvalidParameters(String::from("no public shares to combine")));
+    }
+    if commitments.len() != shares.len() {
+        return Err(Error::InvalidParameters(format!("{} commitments for {} public shares", commitments.len(), shares.len())));
+    }
+    check_len("a", a.len(), k)?;
+    for row in a.iter() {
+        check_vec(row, params)?;
+    }
+    let mut t = vec![Polynomial::new(vec![]); k];
+    for (i, share) in shares.iter().enumerate() {
+        if shares[..i].iter().any(|s| s.index == share.index) {
+            return Err(Error::InvalidParameters(format!("party index {} is repeated", share.index)));
+        }
+        if !commitments.iter().any(|c| *c == share.commitment()) {
+            return Err(Error::InvalidParameters(format!("public share of party {} does not match its commitment", share.index)));
+        }
+        check_vec(&share.t, params)?;
+        t = add_vec(&t, &share.t, q, f);
+    }
+    Ok((a.to_vec(), t))
+}
+
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+struct RawPublicShare {
+    index: i64,
+    t: Vec<Vec<i64>>,
+}
+
+#[cfg(feature = "serde")]
+impl From<PublicShare> for RawPublicShare {
+    fn from(share: PublicShare) -> Self {
+        RawPublicShare { index: share.index, t: share.t.iter().map(|p| p.coeffs().to_vec()).collect() }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<RawPublicShare> for PublicShare {
+    fn from(raw: RawPublicShare) -> Self {
+        // coefficients are range-checked by `joint_public_key`, which needs the parameters
+        PublicShare { index: raw.index, t: raw.t.into_iter().map(Polynomial::new).collect() }
+    }
+}
@@ -1,6 +1,8 @@
 use polynomial_ring::Polynomial;
 use ring_lwe::utils::{polyadd,polysub,nearest_int};
-use crate::utils::{Parameters, add_vec, mul_mat_vec_simple, transpose, mul_vec_simple, gen_small_vector, compress, decompress};
+use crate::utils::{Parameters, add_vec, mul_mat_vec_simple, transpose, mul_vec_simple, gen_small_vector, compress, decompress, SeedStream};
+use crate::types::{check_len, check_poly};
+use crate::error::Error;
 
 /// Encrypt a message using the ring-LWE cryptosystem
 /// # Arguments
@@ -19,9 +21,9 @@ use crate::utils::{Parameters, add_vec, mul_mat_vec_simple, transpose, mul_vec_s
 /// let (u, v) = module_lwe::encrypt::encrypt(&pk.0, &pk.1, &m_b, &params, None);
 /// ```
 pub fn encrypt(
-    a: &Vec<Vec<Polynomial<i64>>>,
-    t: &Vec<Polynomial<i64>>,
-    m_b: &Vec<i64>,
+    a: &[Vec<Polynomial<i64>>],
+    t: &[Polynomial<i64>],
+    m_b: &[i64],
     params: &Parameters,
     seed: Option<u64>
 ) -> (Vec<Polynomial<i64>>, Polynomial<i64>) {
@@ -30,9 +32,10 @@ pub fn encrypt(
     let (n, q, k, f, omega) = (params.n, params.q, params.k, &params.f, params.omega);
     
     //generate random ephermal keys
-    let r = gen_small_vector(n, k, seed);
-    let e1 = gen_small_vector(n, k, seed);
-    let e2 = gen_small_vector(n, 1, seed)[0].clone(); // Single polynomial
+    let mut seeds = SeedStream::new(seed);
+    let r = gen_small_vector(n, k, seeds.next_seed());
+    let e1 = gen_small_vector(n, k, seeds.next_seed());
+    let e2 = gen_small_vector(n, 1, seeds.next_seed())[0].clone(); // Single polynomial
 
     //compute nearest integer to q/2
     let half_q = nearest_int(q,2);
@@ -44,7 +47,7 @@ pub fn encrypt(
     let u = add_vec(&mul_mat_vec_simple(&transpose(a), &r, q, f, omega), &e1, q, f);
 
     // Compute v = t * r + e_2 - m mod q
-    let v = polysub(&polyadd(&mul_vec_simple(t, &r, q, &f, omega), &e2, q, f), &m, q, f);
+    let v = polysub(&polyadd(&mul_vec_simple(t, &r, q, f, omega), &e2, q, f), &m, q, f);
 
     (u, v)
 }
@@ -56,7 +59,7 @@ pub fn encrypt(
 /// * `params` - Parameters for the ring-LWE cryptosystem
 /// * `seed` - random seed
 /// # Returns
-/// * `ciphertext_str` - ciphertext string in base64 encoding
+/// * `ciphertext_str` - ciphertext string in base64 encoding, or an error if the public key is malformed
 /// # Example
 /// ```
 /// let params = module_lwe::utils::Parameters::default();
@@ -64,14 +67,16 @@ pub fn encrypt(
 /// let pk_string = keypair.get("public").unwrap();
 /// let sk_string = keypair.get("secret").unwrap();
 /// let message_string = "Hello, world!".to_string();
-/// let ciphertext_string = module_lwe::encrypt::encrypt_string(&pk_string, &message_string, &params, None);
+/// let ciphertext_string = module_lwe::encrypt::encrypt_string(pk_string, &message_string, &params, None).unwrap();
+/// assert!(module_lwe::encrypt::encrypt_string("not a key", &message_string, &params, None).is_err());
 /// ```
-pub fn encrypt_string(pk_string: &String, message_string: &String, params: &Parameters, seed: Option<u64>) -> String {
+pub fn encrypt_string(pk_string: &str, message_string: &str, params: &Parameters, seed: Option<u64>) -> Result<String, Error> {
     // Get parameters
     let (n, k) = (params.n, params.k);
 
     // Decode and deserialize the base64-encoded public key string
-    let pk_list: Vec<i64> = decompress(pk_string);
+    let pk_list: Vec<i64> = decompress(pk_string)?;
+    check_len("public key", pk_list.len(), (k * k + k) * n)?;
 
     // Parse the public key
     let a: Vec<Vec<Polynomial<i64>>> = pk_list[..k * k * n]
@@ -86,6 +91,9 @@ pub fn encrypt_string(pk_string: &String, message_string: &String, params: &Para
         .map(|coeffs| Polynomial::new(coeffs.to_vec()))
         .collect();
 
+    // Reject out-of-range coefficients, which would overflow the NTT
+    a.iter().flatten().chain(t.iter()).try_for_each(|p| check_poly(p, params))?;
+
     // Parse message
     let message_binary: Vec<i64> = message_string
         .bytes()
@@ -100,8 +108,9 @@ pub fn encrypt_string(pk_string: &String, message_string: &String, params: &Para
 
     // Encrypt each block
     let mut ciphertext_list = vec![];
+    let mut seeds = SeedStream::new(seed);
     for block in message_blocks {
-        let (u, v) = encrypt(&a, &t, &block, params, seed);
+        let (u, v) = encrypt(&a, &t, &block, params, seeds.next_seed());
         let u_flattened: Vec<i64> = u.iter()
             .flat_map(|poly| {
                 let mut coeffs = poly.coeffs().to_vec();
@@ -116,5 +125,5 @@ pub fn encrypt_string(pk_string: &String, message_string: &String, params: &Para
     }
 
     // Serialize and Base64 encode the ciphertext coefficient list
-    compress(&ciphertext_list)
+    Ok(compress(&ciphertext_list))
 }
\ No newline at end of file
@@ -0,0 +1,27 @@
+use std::fmt;
+
+/// Errors returned by the module-LWE library
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// The parameters violate an invariant of the scheme
+    InvalidParameters(String),
+    /// An encoded key or ciphertext is malformed or out of range
+    Decode(String),
+    /// Authenticated decryption failed, e.g. because of a wrong passphrase
+    Authentication,
+    /// Regenerated output differs from a recorded known-answer test
+    KnownAnswer(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::InvalidParameters(msg) => write!(f, "invalid parameters: {}", msg),
+            Error::Decode(msg) => write!(f, "decoding failed: {}", msg),
+            Error::Authentication => write!(f, "authentication failed: wrong passphrase or corrupted data"),
+            Error::KnownAnswer(msg) => write!(f, "known-answer test failed: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
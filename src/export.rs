@@ -0,0 +1,210 @@
+use std::path::{Path, PathBuf};
+use polynomial_ring::Polynomial;
+use crate::attacks::to_plain_lwe;
+use crate::noise::NoiseDistribution;
+use crate::utils::{Parameters, PolyMatrix, PolyVec};
+
+/// Plain LWE instance `t = A s + e mod q` equivalent to a module-LWE public key
+#[derive(Debug, Clone, PartialEq)]
+pub struct LweInstance {
+    /// Degree of the polynomials
+    pub n: usize,
+    /// Ciphertext modulus
+    pub q: i64,
+    /// Module rank
+    pub k: usize,
+    /// Distribution of the secret coefficients
+    pub secret_distribution: NoiseDistribution,
+    /// Distribution of the error coefficients
+    pub error_distribution: NoiseDistribution,
+    /// kn x kn matrix built from the negacyclic rotation matrices of `a`, entries in [0, q)
+    pub a: Vec<Vec<i64>>,
+    /// Coefficients of `t`, entries in [0, q)
+    pub t: Vec<i64>,
+    /// Coefficients of the secret key, when it is known
+    pub s: Option<Vec<i64>>,
+}
+
+impl LweInstance {
+    /// Expand a public key from `keygen::keygen` into a plain LWE instance
+    /// # Arguments
+    /// * `pk` - public key (a, t)
+    /// * `sk` - secret key, exported alongside the instance to check solutions against
+    /// * `params` - Parameters for the module-LWE cryptosystem
+    /// # Returns
+    /// * `instance` - the instance with ternary secret and error distributions, as sampled by `keygen`
+    /// # Example
+    /// ```
+    /// let params = module_lwe::utils::Parameters::preset("toy").unwrap();
+    /// let (pk, sk) = module_lwe::keygen::keygen(&params, None);
+    /// let instance = module_lwe::export::LweInstance::new(&pk, Some(&sk), &params);
+    /// assert_eq!(instance.a.len(), params.k() * params.n());
+    /// assert!(instance.to_sage().contains("A = matrix(ZZ"));
+    /// ```
+    pub fn new(pk: &(PolyMatrix, PolyVec), sk: Option<&[Polynomial<i64>]>, params: &Parameters) -> Self {
+        let (n, q) = (params.n, params.q);
+        let (a, t) = to_plain_lwe(&pk.0, &pk.1, params);
+        let reduce = |row: Vec<i64>| -> Vec<i64> { row.into_iter().map(|c| c.rem_euclid(q)).collect() };
+        let s = sk.map(|sk| {
+            sk.iter()
+                .flat_map(|p| {
+                    let mut c = p.coeffs().to_vec();
+                    c.resize(n, 0);
+                    c
+                })
+                .collect()
+        });
+        LweInstance {
+            n,
+            q,
+            k: params.k,
+            secret_distribution: NoiseDistribution::Ternary,
+            error_distribution: NoiseDistribution::Ternary,
+            a: a.into_iter().map(reduce).collect(),
+            t: reduce(t),
+            s,
+        }
+    }
+
+    /// Dimension kn of the plain LWE secret, which is also the number of samples
+    pub fn dimension(&self) -> usize {
+        self.k * self.n
+    }
+
+    /// Sage script defining `A`, `t` and, when known, `s` over ZZ
+    pub fn to_sage(&self) -> String {
+        let list = |v: &[i64]| -> String {
+            format!("[{}]", v.iter().map(|c| c.to_string()).collect::<Vec<_>>().join(", "))
+        };
+        let mut out = format!("# module-LWE instance: n = {}, q = {}, k = {}\n", self.n, self.q, self.k);
+        out.push_str("# t = A*s + e mod q, A built from the negacyclic rotation matrices of a\n");
+        out.push_str(&format!("# lattice-estimator: {}\n", self.estimator()));
+        out.push_str(&format!("n, q, k = {}, {}, {}\n", self.n, self.q, self.k));
+        let rows: Vec<String> = self.a.iter().map(|row| list(row)).collect();
+        out.push_str(&format!("A = matrix(ZZ, [\n    {}\n])\n", rows.join(",\n    ")));
+        out.push_str(&format!("t = vector(ZZ, {})\n", list(&self.t)));
+        if let Some(s) = &self.s {
+            out.push_str(&format!("s = vector(ZZ, {})\n", list(s)));
+        }
+        out
+    }
+
+    /// `A` in the fplll matrix format read by `fpylll.IntegerMatrix.from_file`
+    pub fn a_fplll(&self) -> String {
+        fplll_matrix(&self.a)
+    }
+
+    /// `t` as a one-row matrix in the fplll matrix format
+    pub fn t_fplll(&self) -> String {
+        fplll_matrix(std::slice::from_ref(&self.t))
+    }
+
+    /// Lattice estimator parameters describing the instance
+    pub fn estimator(&self) -> String {
+        format!("LWE.Parameters(n={}, q={}, Xs={}, Xe={}, m={})",
+            self.dimension(), self.q, estimator_distribution(&self.secret_distribution),
+            estimator_distribution(&self.error_distribution), self.dimension())
+    }
+
+    /// Metadata about the parameters and distributions as JSON
+    pub fn metadata_json(&self) -> String {
+        format!(
+            "{{\n  \"n\": {},\n  \"q\": {},\n  \"k\": {},\n  \"dimension\": {},\n  \"samples\": {},\n  \
+             \"secret\": {},\n  \"error\": {},\n  \"estimator\": \"{}\"\n}}\n",
+            self.n, self.q, self.k, self.dimension(), self.dimension(),
+            distribution_json(&self.secret_distribution), distribution_json(&self.error_distribution),
+            self.estimator()
+        )
+    }
+
+    /// Write the instance to a directory
+    /// Writes `instance.sage`, `A.fplll`, `t.fplll`, `A.npy`, `t.npy`, `s.npy` when the secret is known,
+    /// and `metadata.json`, creating the directory if needed
+    /// # Arguments
+    /// * `dir` - output directory
+    /// # Returns
+    /// * `paths` - the files written
+    pub fn write(&self, dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+        std::fs::create_dir_all(dir)?;
+        let flat: Vec<i64> = self.a.iter().flatten().copied().collect();
+        let mut files = vec![
+            ("instance.sage", self.to_sage().into_bytes()),
+            ("A.fplll", self.a_fplll().into_bytes()),
+            ("t.fplll", self.t_fplll().into_bytes()),
+            ("A.npy", npy(&[self.a.len(), self.dimension()], &flat)),
+            ("t.npy", npy(&[self.t.len()], &self.t)),
+        ];
+        if let Some(s) = &self.s {
+            files.push(("s.npy", npy(&[s.len()], s)));
+        }
+        files.push(("metadata.json", self.metadata_json().into_bytes()));
+        let mut paths = vec![];
+        for (name, contents) in files {
+            let path = dir.join(name);
+            std::fs::write(&path, contents)?;
+            paths.push(path);
+        }
+        Ok(paths)
+    }
+}
+
+/// Matrix in the fplll text format, one bracketed row per line
+fn fplll_matrix(rows: &[Vec<i64>]) -> String {
+    let mut out = String::from("[");
+    for row in rows {
+        out.push_str(&format!("[{}]\n", row.iter().map(|c| c.to_string()).collect::<Vec<_>>().join(" ")));
+    }
+    out.push_str("]\n");
+    out
+}
+
+/// Distribution in the notation of the lattice estimator's `ND` module
+fn estimator_distribution(dist: &NoiseDistribution) -> String {
+    match *dist {
+        NoiseDistribution::Ternary => String::from("ND.Uniform(-1, 1)"),
+        NoiseDistribution::CenteredBinomial(eta) => format!("ND.CenteredBinomial({})", eta),
+        NoiseDistribution::Gaussian(sigma) => format!("ND.DiscreteGaussian({})", sigma),
+    }
+}
+
+/// Distribution as a JSON object with its name, parameter and variance
+fn distribution_json(dist: &NoiseDistribution) -> String {
+    let (name, parameter) = match *dist {
+        NoiseDistribution::Ternary => ("ternary", String::new()),
+        NoiseDistribution::CenteredBinomial(eta) => ("centered-binomial", format!(", \"eta\": {}", eta)),
+        NoiseDistribution::Gaussian(sigma) => ("gaussian", format!(", \"sigma\": {}", sigma)),
+    };
+    format!("{{\"distribution\": \"{}\"{}, \"variance\": {}}}", name, parameter, dist.variance())
+}
+
+/// Encode a row-major array of 64-bit integers in the NumPy `.npy` format (version 1.0)
+/// # Arguments
+/// * `shape` - array dimensions
+/// * `data` - entries in row-major order
+/// # Returns
+/// * `bytes` - contents of the `.npy` file, loadable with `numpy.load`
+/// # Example
+/// ```
+/// let bytes = module_lwe::export::npy(&[2, 2], &[1, 2, 3, 4]);
+/// assert!(bytes.starts_with(b"\x93NUMPY\x01\x00"));
+/// assert_eq!(bytes.len(), 128 + 4 * 8);
+/// ```
+pub fn npy(shape: &[usize], data: &[i64]) -> Vec<u8> {
+    assert_eq!(shape.iter().product::<usize>(), data.len());
+    let shape = match shape {
+        [d] => format!("({},)", d),
+        _ => format!("({})", shape.iter().map(|d| d.to_string()).collect::<Vec<_>>().join(", ")),
+    };
+    let mut header = format!("{{'descr': '<i8', 'fortran_order': False, 'shape': {}, }}", shape);
+    // magic, version and header length take 10 bytes; pad the header with spaces so the data is 64-byte aligned
+    let padding = (64 - (10 + header.len() + 1) % 64) % 64;
+    header.push_str(&" ".repeat(padding));
+    header.push('\n');
+    let mut out = b"\x93NUMPY\x01\x00".to_vec();
+    out.extend_from_slice(&(header.len() as u16).to_le_bytes());
+    out.extend_from_slice(header.as_bytes());
+    for x in data {
+        out.extend_from_slice(&x.to_le_bytes());
+    }
+    out
+}
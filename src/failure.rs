@@ -0,0 +1,193 @@
+use std::collections::BTreeMap;
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use crate::keygen::keygen;
+use crate::encrypt::encrypt;
+use crate::decrypt::decrypt;
+use crate::noise::{NoiseDistribution, measure_noise, noise_variance, failure_probability_log2};
+use crate::utils::{Parameters, SeedStream, derive_seed};
+
+/// Settings of a failure-rate measurement
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FailureRateConfig {
+    /// Number of encrypt/decrypt trials
+    pub trials: u64,
+    /// Number of worker threads
+    pub threads: usize,
+    /// Number of trials run under each freshly generated key pair
+    pub trials_per_key: u64,
+    /// Random seed of the keys, messages and encryptions, or `None` for fresh entropy
+    pub seed: Option<u64>,
+}
+
+impl Default for FailureRateConfig {
+    fn default() -> Self {
+        FailureRateConfig {
+            trials: 10_000,
+            threads: std::thread::available_parallelism().map_or(1, |t| t.get()),
+            trials_per_key: 100,
+            seed: None,
+        }
+    }
+}
+
+/// Outcome of a failure-rate measurement
+#[derive(Debug, Clone, PartialEq)]
+pub struct FailureReport {
+    /// Number of encrypt/decrypt trials
+    pub trials: u64,
+    /// Trials in which at least one coefficient decrypted incorrectly
+    pub failures: u64,
+    /// Incorrectly decrypted coefficients, by coefficient index
+    pub coefficient_failures: Vec<u64>,
+    /// Number of coefficients with each centered noise value
+    pub histogram: BTreeMap<i64, u64>,
+    /// Analytic variance of a noise coefficient, from `noise::noise_variance`
+    pub analytic_variance: f64,
+    /// Analytic log2 failure probability of a ciphertext, from `noise::failure_probability_log2`
+    pub analytic_log2: f64,
+}
+
+impl FailureReport {
+    fn empty(n: usize) -> Self {
+        FailureReport {
+            trials: 0,
+            failures: 0,
+            coefficient_failures: vec![0; n],
+            histogram: BTreeMap::new(),
+            analytic_variance: 0.0,
+            analytic_log2: f64::NEG_INFINITY,
+        }
+    }
+
+    fn merge(&mut self, other: FailureReport) {
+        self.trials += other.trials;
+        self.failures += other.failures;
+        for (total, count) in self.coefficient_failures.iter_mut().zip(other.coefficient_failures) {
+            *total += count;
+        }
+        for (noise, count) in other.histogram {
+            *self.histogram.entry(noise).or_insert(0) += count;
+        }
+    }
+
+    /// Fraction of ciphertexts that failed to decrypt
+    pub fn failure_rate(&self) -> f64 {
+        self.failures as f64 / self.trials.max(1) as f64
+    }
+
+    /// Fraction of coefficients that failed to decrypt
+    pub fn coefficient_failure_rate(&self) -> f64 {
+        let total: u64 = self.coefficient_failures.iter().sum();
+        total as f64 / (self.trials.max(1) * self.coefficient_failures.len().max(1) as u64) as f64
+    }
+
+    /// Analytic failure probability of a ciphertext
+    pub fn analytic_failure_rate(&self) -> f64 {
+        self.analytic_log2.exp2()
+    }
+
+    /// Sample variance of the measured noise coefficients
+    pub fn empirical_variance(&self) -> f64 {
+        let count: u64 = self.histogram.values().sum();
+        if count == 0 {
+            return 0.0;
+        }
+        let (sum, sum_sq) = self.histogram.iter().fold((0.0, 0.0), |(s, s2), (&e, &c)| {
+            (s + e as f64 * c as f64, s2 + (e as f64).powi(2) * c as f64)
+        });
+        let mean = sum / count as f64;
+        sum_sq / count as f64 - mean * mean
+    }
+
+    /// Noise histogram as CSV with columns `noise,count`
+    pub fn histogram_csv(&self) -> String {
+        let mut out = String::from("noise,count\n");
+        for (noise, count) in self.histogram.iter() {
+            out.push_str(&format!("{},{}\n", noise, count));
+        }
+        out
+    }
+
+    /// Per-coefficient failure counts as CSV with columns `coefficient,failures`
+    pub fn coefficients_csv(&self) -> String {
+        let mut out = String::from("coefficient,failures\n");
+        for (i, count) in self.coefficient_failures.iter().enumerate() {
+            out.push_str(&format!("{},{}\n", i, count));
+        }
+        out
+    }
+}
+
+/// Run `trials` encrypt/decrypt trials of random messages on one thread
+fn run_trials(params: &Parameters, trials: u64, trials_per_key: u64, seed: Option<u64>) -> FailureReport {
+    let n = params.n;
+    let mut seeds = SeedStream::new(seed);
+    let mut rng = match seeds.next_seed() {
+        Some(s) => StdRng::seed_from_u64(s),
+        None => StdRng::from_entropy(),
+    };
+    let mut report = FailureReport::empty(n);
+    let mut keys = keygen(params, seeds.next_seed());
+    for trial in 0..trials {
+        if trial > 0 && trial % trials_per_key.max(1) == 0 {
+            keys = keygen(params, seeds.next_seed());
+        }
+        let ((a, t), sk) = &keys;
+        let m_b: Vec<i64> = (0..n).map(|_| rng.gen_range(0..2)).collect();
+        let (u, v) = encrypt(a, t, &m_b, params, seeds.next_seed());
+        let mut decrypted = decrypt(sk, &u, &v, params);
+        decrypted.resize(n, 0);
+        let mut failed = false;
+        for (i, (d, m)) in decrypted.iter().zip(m_b.iter()).enumerate() {
+            if d != m {
+                report.coefficient_failures[i] += 1;
+                failed = true;
+            }
+        }
+        report.failures += failed as u64;
+        for e in measure_noise(sk, &u, &v, &m_b, params) {
+            *report.histogram.entry(e).or_insert(0) += 1;
+        }
+        report.trials += 1;
+    }
+    report
+}
+
+/// Measure the decryption failure rate of `encrypt`/`decrypt` and compare it with the analytic estimate
+/// Keys, randomness and messages are drawn from `config.seed`, so a seeded measurement is reproducible for a fixed
+/// number of threads, or from fresh entropy
+/// # Arguments
+/// * `params` - Parameters for the module-LWE cryptosystem
+/// * `config` - number of trials, threads, trials per key pair and seed
+/// # Returns
+/// * `report` - failure counts, noise histogram and the analytic estimate for ternary secrets and errors
+/// # Example
+/// ```
+/// use module_lwe::failure::{measure_failure_rate, FailureRateConfig};
+/// let params = module_lwe::utils::Parameters::builder().n(16).q(193).k(2).build().unwrap();
+/// let config = FailureRateConfig { trials: 100, threads: 2, trials_per_key: 10, seed: None };
+/// let report = measure_failure_rate(&params, &config);
+/// assert_eq!(report.trials, 100);
+/// assert_eq!(report.histogram.values().sum::<u64>(), 100 * 16);
+/// ```
+pub fn measure_failure_rate(params: &Parameters, config: &FailureRateConfig) -> FailureReport {
+    let threads = config.threads.max(1) as u64;
+    let mut report = std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..threads)
+            .map(|i| {
+                // spread the remainder over the first threads
+                let trials = config.trials / threads + u64::from(i < config.trials % threads);
+                let seed = config.seed.map(|s| derive_seed(s, i));
+                scope.spawn(move || run_trials(params, trials, config.trials_per_key, seed))
+            })
+            .collect();
+        handles.into_iter().fold(FailureReport::empty(params.n), |mut total, handle| {
+            total.merge(handle.join().expect("trial thread panicked"));
+            total
+        })
+    });
+    report.analytic_variance = noise_variance(params, &NoiseDistribution::Ternary, &NoiseDistribution::Ternary);
+    report.analytic_log2 = failure_probability_log2(params, report.analytic_variance);
+    report
+}
@@ -0,0 +1,204 @@
+use std::collections::HashMap;
+use polynomial_ring::Polynomial;
+use ring_lwe::utils::{polysub, polyinv, mod_coeffs};
+use crate::utils::{Parameters, PolyVec, SeedStream};
+use crate::keygen::switching_keygen;
+use crate::homomorphic::{KeySwitchingKey, key_switch, add};
+use crate::error::Error;
+
+/// Galois key-switching keys, one per automorphism X -> X^g
+#[derive(Debug, Clone)]
+pub struct GaloisKeys {
+    /// Key switching sigma_g(sk) back to sk, by Galois element g in [1, 2n)
+    pub keys: HashMap<usize, KeySwitchingKey>,
+}
+
+/// Reduce a Galois element mod 2n, checking that it is odd so that X -> X^g is an automorphism
+fn galois_element(g: usize, params: &Parameters) -> Result<usize, Error> {
+    let g = g % (2 * params.n);
+    if g.is_multiple_of(2) {
+        return Err(Error::InvalidParameters(format!("Galois element {} is not odd mod 2n = {}", g, 2 * params.n)));
+    }
+    Ok(g)
+}
+
+/// Apply the automorphism X -> X^g of Z_q[X]/(X^n+1) to a polynomial
+/// X^j maps to X^(g*j mod 2n), and X^n = -1 folds exponents n..2n back with a sign flip
+/// # Arguments
+/// * `p` - polynomial
+/// * `g` - odd Galois element
+/// * `params` - Parameters for the module-LWE cryptosystem
+/// # Returns
+/// * `sigma_g(p)` - p(X^g) mod (X^n + 1, q)
+/// # Example
+/// ```
+/// use polynomial_ring::Polynomial;
+/// let params = module_lwe::utils::Parameters::builder().n(4).q(17).k(1).build().unwrap();
+/// // X -> X^3: 1 + X -> 1 + X^3, and X^2 -> X^6 = -X^2
+/// let p = Polynomial::new(vec![1, 1, 1]);
+/// assert_eq!(module_lwe::galois::automorphism(&p, 3, &params), Polynomial::new(vec![1, 0, -1, 1]));
+/// ```
+pub fn automorphism(p: &Polynomial<i64>, g: usize, params: &Parameters) -> Polynomial<i64> {
+    let (n, q) = (params.n, params.q);
+    let mut coeffs = vec![0i64; n];
+    for (j, &c) in p.coeffs().iter().enumerate() {
+        let e = g * j % (2 * n);
+        if e < n {
+            coeffs[e] = (coeffs[e] + c) % q;
+        } else {
+            coeffs[e - n] = (coeffs[e - n] - c) % q;
+        }
+    }
+    mod_coeffs(Polynomial::new(coeffs), q)
+}
+
+/// Galois element that rotates both rows of `batch::BatchEncoder` slots left by `steps`
+/// # Arguments
+/// * `steps` - rotation amount, negative to rotate right
+/// * `params` - Parameters for the module-LWE cryptosystem
+/// # Returns
+/// * `g` - 5^steps mod 2n
+pub fn rotation_element(steps: isize, params: &Parameters) -> usize {
+    let n = params.n;
+    // 5 has order n/2 mod 2n
+    let steps = steps.rem_euclid((n / 2).max(1) as isize) as usize;
+    (0..steps).fold(1, |g, _| g * 5 % (2 * n))
+}
+
+/// Galois element that swaps the two rows of `batch::BatchEncoder` slots
+pub fn row_swap_element(params: &Parameters) -> usize {
+    2 * params.n - 1
+}
+
+/// Galois elements needed by `sum_all_slots`: rotations by 1, 2, 4, ..., n/4 and the row swap
+pub fn sum_elements(params: &Parameters) -> Vec<usize> {
+    let half = params.n / 2;
+    let mut elements: Vec<usize> = (0..)
+        .map(|i| 1isize << i)
+        .take_while(|&s| (s as usize) < half)
+        .map(|s| rotation_element(s, params))
+        .collect();
+    elements.push(row_swap_element(params));
+    elements
+}
+
+/// Generate Galois keys for a set of automorphisms
+/// # Arguments
+/// * `sk` - secret key
+/// * `elements` - odd Galois elements, e.g. from `rotation_element` or `sum_elements`
+/// * `base` - gadget decomposition base
+/// * `params` - Parameters for the module-LWE cryptosystem
+/// * `seed` - random seed
+/// # Returns
+/// * `gk` - key switching sigma_g(sk) to sk for every element, or an error if an element is even
+pub fn galois_keygen(
+    sk: &[Polynomial<i64>],
+    elements: &[usize],
+    base: i64,
+    params: &Parameters,
+    seed: Option<u64>
+) -> Result<GaloisKeys, Error> {
+    let mut keys = HashMap::new();
+    let mut seeds = SeedStream::new(seed);
+    for &g in elements {
+        let g = galois_element(g, params)?;
+        let src: PolyVec = sk.iter().map(|s| automorphism(s, g, params)).collect();
+        keys.insert(g, switching_keygen(&src, sk, base, params, seeds.next_seed()));
+    }
+    Ok(GaloisKeys { keys })
+}
+
+/// Apply an automorphism to a ciphertext and switch back to the original key
+/// (sigma_g(u), sigma_g(v)) decrypts under sigma_g(sk); key switching sigma_g(u) gives (u', v') with phase close to
+/// sigma_g(sk)*sigma_g(u), so (-u', sigma_g(v) - v') decrypts to sigma_g(m) under sk
+/// # Arguments
+/// * `ct` - ciphertext (u, v)
+/// * `g` - odd Galois element
+/// * `gk` - Galois keys containing g
+/// * `params` - Parameters for the module-LWE cryptosystem
+/// # Returns
+/// * `(u, v)` - ciphertext of m(X^g), or an error if there is no key for g (g = 1 needs none)
+/// # Example
+/// ```
+/// use module_lwe::galois::{galois_keygen, apply_galois};
+/// let params = module_lwe::utils::Parameters::builder().n(16).q(1073707009).k(2).build().unwrap();
+/// let (pk, sk) = module_lwe::keygen::keygen(&params, None);
+/// let gk = galois_keygen(&sk, &[3], 1 << 10, &params, None).unwrap();
+/// // X -> X^3 moves the bit at X^1 to X^3
+/// let ct = module_lwe::encrypt::encrypt(&pk.0, &pk.1, &[0, 1], &params, None);
+/// let (u, v) = apply_galois(&ct, 3, &gk, &params).unwrap();
+/// assert_eq!(module_lwe::decrypt::decrypt(&sk, &u, &v, &params)[..4], [0, 0, 0, 1]);
+/// ```
+pub fn apply_galois(
+    ct: &(PolyVec, Polynomial<i64>),
+    g: usize,
+    gk: &GaloisKeys,
+    params: &Parameters
+) -> Result<(PolyVec, Polynomial<i64>), Error> {
+    let (q, f) = (params.q, &params.f);
+    let g = galois_element(g, params)?;
+    if g == 1 {
+        // the identity needs no key
+        return Ok(ct.clone());
+    }
+    let key = gk.keys.get(&g)
+        .ok_or_else(|| Error::InvalidParameters(format!("no Galois key for element {}", g)))?;
+    let u: PolyVec = ct.0.iter().map(|p| automorphism(p, g, params)).collect();
+    let v = automorphism(&ct.1, g, params);
+    let (u, v_switch) = key_switch(key, &u, params);
+    Ok((u.iter().map(|p| polyinv(p, q)).collect(), polysub(&v, &v_switch, q, f)))
+}
+
+/// Rotate both rows of batched slots left by `steps`
+/// # Arguments
+/// * `ct` - ciphertext of `batch::BatchEncoder` slots
+/// * `steps` - rotation amount, negative to rotate right
+/// * `gk` - Galois keys containing `rotation_element(steps, params)`
+/// * `params` - Parameters for the module-LWE cryptosystem
+/// # Returns
+/// * `(u, v)` - ciphertext with slot i of each row replaced by slot i + steps of the same row
+pub fn rotate_rows(
+    ct: &(PolyVec, Polynomial<i64>),
+    steps: isize,
+    gk: &GaloisKeys,
+    params: &Parameters
+) -> Result<(PolyVec, Polynomial<i64>), Error> {
+    apply_galois(ct, rotation_element(steps, params), gk, params)
+}
+
+/// Sum all batched slots, leaving the total in every slot
+/// Adds log2(n/2) row rotations by doubling amounts and then the row swap; for a coefficient encoding the same
+/// sum over the Galois group is the trace, n times the constant coefficient
+/// # Arguments
+/// * `ct` - ciphertext of `batch::BatchEncoder` slots
+/// * `gk` - Galois keys for `sum_elements(params)`
+/// * `params` - Parameters for the module-LWE cryptosystem
+/// # Returns
+/// * `(u, v)` - ciphertext whose every slot is the sum of the input slots mod t
+/// # Example
+/// ```
+/// use module_lwe::batch::{BatchEncoder, encrypt, decrypt};
+/// use module_lwe::galois::{galois_keygen, sum_elements, sum_all_slots};
+/// let params = module_lwe::utils::Parameters::builder().n(16).q(1073707009).k(2).build().unwrap();
+/// let (pk, sk) = module_lwe::keygen::keygen(&params, None);
+/// let gk = galois_keygen(&sk, &sum_elements(&params), 1 << 10, &params, None).unwrap();
+/// let encoder = BatchEncoder::new(&params, 97).unwrap();
+/// let ct = encrypt(&pk, &encoder.encode(&[1, 2, 3, 4]).unwrap(), 97, &params, None).unwrap();
+/// let total = sum_all_slots(&ct, &gk, &params).unwrap();
+/// assert_eq!(encoder.decode(&decrypt(&sk, &total, 97, &params)), vec![10; 16]);
+/// ```
+pub fn sum_all_slots(
+    ct: &(PolyVec, Polynomial<i64>),
+    gk: &GaloisKeys,
+    params: &Parameters
+) -> Result<(PolyVec, Polynomial<i64>), Error> {
+    let half = params.n / 2;
+    let mut acc = ct.clone();
+    let mut steps = 1;
+    while steps < half {
+        acc = add(&acc, &rotate_rows(&acc, steps as isize, gk, params)?, params);
+        steps *= 2;
+    }
+    let swapped = apply_galois(&acc, row_swap_element(params), gk, params)?;
+    Ok(add(&acc, &swapped, params))
+}
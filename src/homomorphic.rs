@@ -0,0 +1,353 @@
+use polynomial_ring::Polynomial;
+use ring_lwe::utils::{polyadd, mod_coeffs};
+use crate::utils::{Parameters, PolyMatrix, PolyVec, add_vec, mul_poly, gadget_decompose, gen_bounded_vector, SeedStream};
+use crate::encrypt::encrypt;
+use crate::error::Error;
+
+/// Key-switching key from a list of source polynomials to a target secret key
+/// `keys[j][l] = (a, b)` with `b = sk*a + e + src_j * base^l`, so its phase `b - sk*a` is `src_j * base^l + e`
+#[derive(Debug, Clone)]
+pub struct KeySwitchingKey {
+    /// Gadget decomposition base
+    pub base: i64,
+    /// Number of gadget digits
+    pub levels: usize,
+    /// Encryptions of `src_j * base^l` under the target key
+    pub keys: Vec<Vec<(PolyVec, Polynomial<i64>)>>,
+}
+
+/// Degree-2 ciphertext produced by tensoring, decrypting as `c0 - sk*c1 + sum_{i<=j} sk_i*sk_j*c2_ij`
+#[derive(Debug, Clone)]
+pub struct TensorCiphertext {
+    /// Constant term
+    pub c0: Polynomial<i64>,
+    /// Linear terms, one per secret key component
+    pub c1: PolyVec,
+    /// Quadratic terms, packed over the pairs i <= j in row-major order
+    pub c2: PolyVec,
+}
+
+/// Add two ciphertexts
+/// # Arguments
+/// * `ct0` - ciphertext (u0, v0)
+/// * `ct1` - ciphertext (u1, v1)
+/// * `params` - Parameters for the module-LWE cryptosystem
+/// # Returns
+/// * `(u, v)` - ciphertext encrypting the sum of the plaintexts mod 2
+pub fn add(
+    ct0: &(PolyVec, Polynomial<i64>),
+    ct1: &(PolyVec, Polynomial<i64>),
+    params: &Parameters
+) -> (PolyVec, Polynomial<i64>) {
+    let (q, f) = (params.q, &params.f);
+    (add_vec(&ct0.0, &ct1.0, q, f), polyadd(&ct0.1, &ct1.1, q, f))
+}
+
+/// Apply a key-switching key to a list of polynomials
+/// # Arguments
+/// * `ksk` - key-switching key for the source polynomials src_j
+/// * `c` - one polynomial per source polynomial
+/// * `params` - Parameters for the module-LWE cryptosystem
+/// # Returns
+/// * `(u, v)` - ciphertext under the target key whose phase `v - sk*u` is close to `sum_j src_j * c_j`
+pub fn key_switch(ksk: &KeySwitchingKey, c: &[Polynomial<i64>], params: &Parameters) -> (PolyVec, Polynomial<i64>) {
+    assert_eq!(ksk.keys.len(), c.len());
+    let (q, k, f, omega) = (params.q, params.k, &params.f, params.omega);
+    let mut u = vec![Polynomial::new(vec![]); k];
+    let mut v = Polynomial::new(vec![]);
+    for (keys, cj) in ksk.keys.iter().zip(c.iter()) {
+        let digits = gadget_decompose(cj, ksk.base, ksk.levels, q);
+        for ((a, b), d) in keys.iter().zip(digits.iter()) {
+            if d.coeffs().is_empty() {
+                continue;
+            }
+            for (ui, ai) in u.iter_mut().zip(a.iter()) {
+                *ui = polyadd(ui, &mul_poly(ai, d, q, f, omega), q, f);
+            }
+            v = polyadd(&v, &mul_poly(b, d, q, f, omega), q, f);
+        }
+    }
+    (u, v)
+}
+
+/// Negacyclic product of two polynomials over the integers
+fn polymul_int(x: &[i128], y: &[i128], n: usize) -> Vec<i128> {
+    let mut result = vec![0i128; n];
+    for (i, &a) in x.iter().enumerate() {
+        if a == 0 {
+            continue;
+        }
+        for (j, &b) in y.iter().enumerate() {
+            if i + j < n {
+                result[i + j] += a * b;
+            } else {
+                result[i + j - n] -= a * b;
+            }
+        }
+    }
+    result
+}
+
+/// Centered coefficients of a polynomial in R_q, padded to length n
+fn centered(p: &Polynomial<i64>, n: usize, q: i64) -> Vec<i128> {
+    let mut coeffs: Vec<i128> = p.coeffs()
+        .iter()
+        .map(|&c| {
+            let c = c.rem_euclid(q);
+            (if c > q / 2 { c - q } else { c }) as i128
+        })
+        .collect();
+    coeffs.resize(n, 0);
+    coeffs
+}
+
+/// Round t*x/q to the nearest integer and reduce mod q
+fn scale_round(x: &[i128], t: i64, q: i64) -> Polynomial<i64> {
+    let (t, q) = (t as i128, q as i128);
+    let coeffs: Vec<i64> = x.iter()
+        .map(|&c| {
+            let num = c * t;
+            let r = if num >= 0 { (num + q / 2) / q } else { -((-num + q / 2) / q) };
+            r.rem_euclid(q) as i64
+        })
+        .collect();
+    mod_coeffs(Polynomial::new(coeffs), q as i64)
+}
+
+/// Tensor two ciphertexts and rescale by t/q (t = 2)
+/// # Arguments
+/// * `ct0` - ciphertext (u0, v0)
+/// * `ct1` - ciphertext (u1, v1)
+/// * `params` - Parameters for the module-LWE cryptosystem
+/// # Returns
+/// * `ct2` - degree-2 ciphertext encrypting the product of the plaintexts
+pub fn tensor(
+    ct0: &(PolyVec, Polynomial<i64>),
+    ct1: &(PolyVec, Polynomial<i64>),
+    params: &Parameters
+) -> TensorCiphertext {
+    tensor_with_modulus(ct0, ct1, 2, params)
+}
+
+/// Tensor two ciphertexts with plaintext modulus t, as produced by `batch::encrypt`, and rescale by t/q
+/// # Arguments
+/// * `ct0` - ciphertext (u0, v0)
+/// * `ct1` - ciphertext (u1, v1)
+/// * `t` - plaintext modulus
+/// * `params` - Parameters for the module-LWE cryptosystem
+/// # Returns
+/// * `ct2` - degree-2 ciphertext encrypting the product of the plaintexts mod t
+pub fn tensor_with_modulus(
+    ct0: &(PolyVec, Polynomial<i64>),
+    ct1: &(PolyVec, Polynomial<i64>),
+    t: i64,
+    params: &Parameters
+) -> TensorCiphertext {
+    let (n, q, k) = (params.n, params.q, params.k);
+    let u0: Vec<Vec<i128>> = ct0.0.iter().map(|p| centered(p, n, q)).collect();
+    let u1: Vec<Vec<i128>> = ct1.0.iter().map(|p| centered(p, n, q)).collect();
+    let v0 = centered(&ct0.1, n, q);
+    let v1 = centered(&ct1.1, n, q);
+
+    // (v0 - sk*u0)(v1 - sk*u1) = v0*v1 - sk*(v0*u1 + v1*u0) + sum_ij sk_i*sk_j*u0_i*u1_j
+    // the product encrypts m0*m1 with the opposite sign convention, so every term is negated
+    let neg = |x: Vec<i128>| -> Vec<i128> { x.into_iter().map(|c| -c).collect() };
+    let c0 = scale_round(&neg(polymul_int(&v0, &v1, n)), t, q);
+    let c1: PolyVec = (0..k)
+        .map(|i| {
+            let sum: Vec<i128> = polymul_int(&v0, &u1[i], n)
+                .iter()
+                .zip(polymul_int(&v1, &u0[i], n))
+                .map(|(a, b)| a + b)
+                .collect();
+            scale_round(&neg(sum), t, q)
+        })
+        .collect();
+    let mut c2 = vec![];
+    for i in 0..k {
+        for j in i..k {
+            let mut sum = polymul_int(&u0[i], &u1[j], n);
+            if i != j {
+                for (s, c) in sum.iter_mut().zip(polymul_int(&u0[j], &u1[i], n)) {
+                    *s += c;
+                }
+            }
+            c2.push(scale_round(&neg(sum), t, q));
+        }
+    }
+    TensorCiphertext { c0, c1, c2 }
+}
+
+/// Relinearize a degree-2 ciphertext back to a standard ciphertext
+/// # Arguments
+/// * `ct2` - degree-2 ciphertext
+/// * `rlk` - relinearization key from `keygen::relin_keygen`
+/// * `params` - Parameters for the module-LWE cryptosystem
+/// # Returns
+/// * `(u, v)` - ciphertext decryptable with `decrypt::decrypt`
+pub fn relinearize(ct2: &TensorCiphertext, rlk: &KeySwitchingKey, params: &Parameters) -> (PolyVec, Polynomial<i64>) {
+    let (q, f) = (params.q, &params.f);
+    let (u, v) = key_switch(rlk, &ct2.c2, params);
+    (add_vec(&ct2.c1, &u, q, f), polyadd(&ct2.c0, &v, q, f))
+}
+
+/// Multiply two ciphertexts
+/// # Arguments
+/// * `ct0` - ciphertext (u0, v0)
+/// * `ct1` - ciphertext (u1, v1)
+/// * `rlk` - relinearization key from `keygen::relin_keygen`
+/// * `params` - Parameters for the module-LWE cryptosystem
+/// # Returns
+/// * `(u, v)` - ciphertext encrypting the product of the plaintext polynomials mod 2
+/// # Example
+/// ```
+/// let params = module_lwe::utils::Parameters::builder().n(16).q(1073707009).k(2).build().unwrap();
+/// let (pk, sk) = module_lwe::keygen::keygen(&params, None);
+/// let rlk = module_lwe::keygen::relin_keygen(&sk, 1 << 10, &params, None);
+/// let ct0 = module_lwe::encrypt::encrypt(&pk.0, &pk.1, &[1], &params, None);
+/// let ct1 = module_lwe::encrypt::encrypt(&pk.0, &pk.1, &[1], &params, None);
+/// let (u, v) = module_lwe::homomorphic::mul(&ct0, &ct1, &rlk, &params);
+/// assert_eq!(module_lwe::decrypt::decrypt(&sk, &u, &v, &params)[0], 1);
+/// ```
+pub fn mul(
+    ct0: &(PolyVec, Polynomial<i64>),
+    ct1: &(PolyVec, Polynomial<i64>),
+    rlk: &KeySwitchingKey,
+    params: &Parameters
+) -> (PolyVec, Polynomial<i64>) {
+    relinearize(&tensor(ct0, ct1, params), rlk, params)
+}
+
+/// Multiply two ciphertexts with plaintext modulus t
+/// With `batch::BatchEncoder` plaintexts this multiplies the slots element-wise
+/// # Arguments
+/// * `ct0` - ciphertext (u0, v0)
+/// * `ct1` - ciphertext (u1, v1)
+/// * `t` - plaintext modulus
+/// * `rlk` - relinearization key from `keygen::relin_keygen`
+/// * `params` - Parameters for the module-LWE cryptosystem
+/// # Returns
+/// * `(u, v)` - ciphertext encrypting the product of the plaintext polynomials mod t, for `batch::decrypt`
+pub fn mul_with_modulus(
+    ct0: &(PolyVec, Polynomial<i64>),
+    ct1: &(PolyVec, Polynomial<i64>),
+    t: i64,
+    rlk: &KeySwitchingKey,
+    params: &Parameters
+) -> (PolyVec, Polynomial<i64>) {
+    relinearize(&tensor_with_modulus(ct0, ct1, t, params), rlk, params)
+}
+
+/// Rerandomize a ciphertext by adding a fresh encryption of zero under the same public key
+/// The result decrypts to the same message, and its (u, v) is independent of the input's apart from the noise:
+/// every call adds the noise of one fresh ciphertext, so `noise::max_rerandomizations` bounds how often it can be applied.
+/// The input's noise is still part of the output; use a sanitizing step when the noise itself must not leak.
+/// # Arguments
+/// * `pk` - public key (a, t) the ciphertext was encrypted under
+/// * `ct` - ciphertext (u, v)
+/// * `params` - Parameters for the module-LWE cryptosystem
+/// * `seed` - random seed
+/// # Returns
+/// * `(u, v)` - ciphertext of the same message
+/// # Example
+/// ```
+/// let params = module_lwe::utils::Parameters::default();
+/// let (pk, sk) = module_lwe::keygen::keygen(&params, None);
+/// let ct = module_lwe::encrypt::encrypt(&pk.0, &pk.1, &[1, 0, 1], &params, None);
+/// let (u, v) = module_lwe::homomorphic::rerandomize(&pk, &ct, &params, None);
+/// assert_ne!(u, ct.0);
+/// assert_eq!(module_lwe::decrypt::decrypt(&sk, &u, &v, &params)[..3], [1, 0, 1]);
+/// ```
+pub fn rerandomize(
+    pk: &(PolyMatrix, PolyVec),
+    ct: &(PolyVec, Polynomial<i64>),
+    params: &Parameters,
+    seed: Option<u64>
+) -> (PolyVec, Polynomial<i64>) {
+    let zero = encrypt(&pk.0, &pk.1, &[0], params, seed);
+    add(ct, &zero, params)
+}
+
+/// Smudging bound that hides a noise term of magnitude at most `noise_bound` up to statistical distance 2^-lambda
+/// Shifting the uniform distribution on [-B, B] by x changes it by |x| / (2B + 1) per coefficient,
+/// so B = n * noise_bound * 2^(lambda - 1) covers all n coefficients
+/// # Arguments
+/// * `noise_bound` - bound on the noise of the ciphertext to sanitize, e.g. j * (2kn + 1) after adding j fresh ciphertexts
+/// * `lambda` - statistical security parameter in bits
+/// * `params` - Parameters for the module-LWE cryptosystem
+/// # Returns
+/// * `bound` - smudging bound B, or `None` if it does not fit in an i64
+pub fn smudging_bound(noise_bound: i64, lambda: u32, params: &Parameters) -> Option<i64> {
+    let scale = if lambda == 0 { Some(1) } else { 1i64.checked_shl(lambda - 1).filter(|&s| s > 0) };
+    (params.n as i64).checked_mul(noise_bound.max(1))?.checked_mul(scale?)
+}
+
+/// Largest statistical security parameter for which `sanitize` still decrypts correctly
+/// The output noise is at most noise_bound + (2kn + 1) + smudging bound, which must stay below q/4
+/// # Arguments
+/// * `noise_bound` - bound on the noise of the ciphertext to sanitize
+/// * `params` - Parameters for the module-LWE cryptosystem
+/// # Returns
+/// * `lambda` - largest admissible lambda, or `None` if even lambda = 0 does not fit
+/// # Example
+/// ```
+/// use module_lwe::utils::Parameters;
+/// use module_lwe::homomorphic::max_sanitization_security;
+/// let params = Parameters::builder().n(16).q(1073707009).k(2).build().unwrap();
+/// assert_eq!(max_sanitization_security(2 * 65, &params), Some(17));
+/// assert_eq!(max_sanitization_security(2 * 8193, &Parameters::default()), None);
+/// ```
+pub fn max_sanitization_security(noise_bound: i64, params: &Parameters) -> Option<u32> {
+    let (n, q, k) = (params.n as i64, params.q, params.k as i64);
+    let fits = |lambda: u32| smudging_bound(noise_bound, lambda, params)
+        .and_then(|b| b.checked_add(noise_bound)?.checked_add(2 * k * n + 1))
+        .is_some_and(|total| total < q / 4);
+    (0..64).take_while(|&lambda| fits(lambda)).last()
+}
+
+/// Sanitize a ciphertext, e.g. the result of homomorphic additions, so that it reveals nothing beyond the message
+/// Rerandomizes (u, v) with a fresh encryption of zero and floods v with noise uniform in [-B, B] for
+/// B = `smudging_bound(noise_bound, lambda, params)`, which hides the input's noise up to statistical distance 2^-lambda.
+/// Needs q/4 > noise_bound + (2kn + 1) + B, see `max_sanitization_security`; the default q = 12289 is far too small
+/// for meaningful lambda, while q = 1073707009 with n = 16, k = 2 allows lambda = 17 after one addition.
+/// # Arguments
+/// * `pk` - public key (a, t) the ciphertext is encrypted under
+/// * `ct` - ciphertext (u, v)
+/// * `noise_bound` - bound on the noise of `ct`
+/// * `lambda` - statistical security parameter in bits
+/// * `params` - Parameters for the module-LWE cryptosystem
+/// * `seed` - random seed
+/// # Returns
+/// * `(u, v)` - sanitized ciphertext of the same message, or an error if the noise would no longer decrypt
+/// # Example
+/// ```
+/// use module_lwe::homomorphic::{add, sanitize};
+/// let params = module_lwe::utils::Parameters::builder().n(16).q(1073707009).k(2).build().unwrap();
+/// let (pk, sk) = module_lwe::keygen::keygen(&params, None);
+/// let ct0 = module_lwe::encrypt::encrypt(&pk.0, &pk.1, &[1, 1], &params, None);
+/// let ct1 = module_lwe::encrypt::encrypt(&pk.0, &pk.1, &[0, 1], &params, None);
+/// let fresh = 2 * 2 * 16 + 1;
+/// let (u, v) = sanitize(&pk, &add(&ct0, &ct1, &params), 2 * fresh, 16, &params, None).unwrap();
+/// assert_eq!(module_lwe::decrypt::decrypt(&sk, &u, &v, &params)[..2], [1, 0]);
+/// assert!(sanitize(&pk, &ct0, fresh, 40, &params, None).is_err());
+/// ```
+pub fn sanitize(
+    pk: &(PolyMatrix, PolyVec),
+    ct: &(PolyVec, Polynomial<i64>),
+    noise_bound: i64,
+    lambda: u32,
+    params: &Parameters,
+    seed: Option<u64>
+) -> Result<(PolyVec, Polynomial<i64>), Error> {
+    let (n, q, f) = (params.n, params.q, &params.f);
+    if max_sanitization_security(noise_bound, params).is_none_or(|max| lambda > max) {
+        return Err(Error::InvalidParameters(format!(
+            "smudging for lambda = {} and noise bound {} does not fit below q/4 for q = {}", lambda, noise_bound, q
+        )));
+    }
+    let bound = smudging_bound(noise_bound, lambda, params).expect("checked by max_sanitization_security");
+    let mut seeds = SeedStream::new(seed);
+    let (u, v) = rerandomize(pk, ct, params, seeds.next_seed());
+    let smudging = gen_bounded_vector(n, 1, bound, seeds.next_seed()).remove(0);
+    Ok((u, polyadd(&v, &smudging, q, f)))
+}
@@ -0,0 +1,168 @@
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use crate::keygen::keygen;
+use crate::encrypt::encrypt_string;
+use crate::decrypt::decrypt_string;
+use crate::types::{PublicKey, SecretKey};
+use crate::utils::{Parameters, SeedStream};
+use crate::error::Error;
+
+/// One known-answer entry: everything derived from a single seed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KatEntry {
+    /// Index of the entry in its file
+    pub count: usize,
+    /// Seed of the message, keygen and encryption
+    pub seed: u64,
+    /// Message bytes
+    pub msg: Vec<u8>,
+    /// DER encoding of the public key
+    pub pk: Vec<u8>,
+    /// DER encoding of the secret key
+    pub sk: Vec<u8>,
+    /// Ciphertext as returned by `encrypt_string`
+    pub ct: String,
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(s: &str) -> Result<Vec<u8>, Error> {
+    if !s.len().is_multiple_of(2) || !s.is_ascii() {
+        return Err(Error::Decode(String::from("invalid hex string")));
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).map_err(|_| Error::Decode(String::from("invalid hex string"))))
+        .collect()
+}
+
+/// Seed of the entry with the given index
+fn entry_seed(count: usize) -> u64 {
+    0x006b_6174_0000 + count as u64
+}
+
+/// Printable message of up to two blocks derived from the seed
+fn entry_message(params: &Parameters, seed: u64) -> Vec<u8> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let len = rng.gen_range(1..=params.n / 4);
+    (0..len).map(|_| rng.gen_range(b' '..=b'~')).collect()
+}
+
+/// Generate the known-answer entry for a seed
+/// # Arguments
+/// * `params` - Parameters for the module-LWE cryptosystem
+/// * `count` - index of the entry
+/// * `seed` - seed of the message, keygen and encryption
+/// # Returns
+/// * `entry` - message, keys and ciphertext
+pub fn generate_entry(params: &Parameters, count: usize, seed: u64) -> KatEntry {
+    let mut seeds = SeedStream::new(Some(seed));
+    let msg = entry_message(params, seed);
+    let (pk, sk) = keygen(params, seeds.next_seed());
+    let pk = PublicKey::new(params, pk);
+    let sk = SecretKey::new(params, sk);
+    let message = std::str::from_utf8(&msg).expect("message is printable ascii");
+    let ct = encrypt_string(&pk.to_base64(), message, params, seeds.next_seed())
+        .expect("public key is well formed");
+    KatEntry { count, seed, msg, pk: pk.to_der(), sk: sk.to_der(), ct }
+}
+
+/// Generate a known-answer file
+/// # Arguments
+/// * `name` - name of the parameter set, recorded in the file header
+/// * `params` - Parameters for the module-LWE cryptosystem
+/// * `entries` - number of entries
+/// # Returns
+/// * `kat` - text of the file, one `key = value` line per field and a blank line between entries
+/// # Example
+/// ```
+/// use module_lwe::utils::Parameters;
+/// let params = Parameters::preset("toy").unwrap();
+/// let kat = module_lwe::kat::generate("toy", &params, 2);
+/// assert_eq!(module_lwe::kat::verify(&kat), Ok(2));
+/// ```
+pub fn generate(name: &str, params: &Parameters, entries: usize) -> String {
+    let mut out = format!("# module-LWE known-answer tests: {}\n\nn = {}\nq = {}\nk = {}\n", name, params.n, params.q, params.k);
+    for count in 0..entries {
+        let entry = generate_entry(params, count, entry_seed(count));
+        out.push_str(&format!(
+            "\ncount = {}\nseed = {}\nmsg = {}\npk = {}\nsk = {}\nct = {}\n",
+            entry.count, entry.seed, to_hex(&entry.msg), to_hex(&entry.pk), to_hex(&entry.sk), entry.ct
+        ));
+    }
+    out
+}
+
+/// Parse a known-answer file
+/// # Arguments
+/// * `kat` - text of the file
+/// # Returns
+/// * `(params, entries)` - the parameters from the header and the recorded entries
+pub fn parse(kat: &str) -> Result<(Parameters, Vec<KatEntry>), Error> {
+    let mut header: Vec<(&str, &str)> = vec![];
+    let mut entries: Vec<Vec<(&str, &str)>> = vec![];
+    for line in kat.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+        let (key, value) = line.split_once('=')
+            .map(|(key, value)| (key.trim(), value.trim()))
+            .ok_or_else(|| Error::Decode(format!("expected key = value, found {}", line)))?;
+        if key == "count" {
+            entries.push(vec![]);
+        }
+        entries.last_mut().unwrap_or(&mut header).push((key, value));
+    }
+    let field = |fields: &[(&str, &str)], key: &str| -> Result<String, Error> {
+        fields.iter()
+            .find(|(k, _)| *k == key)
+            .map(|(_, v)| v.to_string())
+            .ok_or_else(|| Error::Decode(format!("missing field {}", key)))
+    };
+    let number = |fields: &[(&str, &str)], key: &str| -> Result<u64, Error> {
+        field(fields, key)?.parse().map_err(|_| Error::Decode(format!("invalid field {}", key)))
+    };
+    let params = Parameters::builder()
+        .n(number(&header, "n")? as usize)
+        .q(number(&header, "q")? as i64)
+        .k(number(&header, "k")? as usize)
+        .build()?;
+    let entries = entries.iter()
+        .map(|fields| Ok(KatEntry {
+            count: number(fields, "count")? as usize,
+            seed: number(fields, "seed")?,
+            msg: from_hex(&field(fields, "msg")?)?,
+            pk: from_hex(&field(fields, "pk")?)?,
+            sk: from_hex(&field(fields, "sk")?)?,
+            ct: field(fields, "ct")?,
+        }))
+        .collect::<Result<Vec<KatEntry>, Error>>()?;
+    Ok((params, entries))
+}
+
+/// Replay a known-answer file
+/// Every entry is regenerated from its seed and compared field by field, and its ciphertext must decrypt to its message
+/// # Arguments
+/// * `kat` - text of the file
+/// # Returns
+/// * `entries` - number of entries checked, or the first mismatch
+pub fn verify(kat: &str) -> Result<usize, Error> {
+    let (params, entries) = parse(kat)?;
+    for entry in entries.iter() {
+        let expected = generate_entry(&params, entry.count, entry.seed);
+        for (name, matches) in [
+            ("msg", expected.msg == entry.msg),
+            ("pk", expected.pk == entry.pk),
+            ("sk", expected.sk == entry.sk),
+            ("ct", expected.ct == entry.ct),
+        ] {
+            if !matches {
+                return Err(Error::KnownAnswer(format!("{} of entry {} differs", name, entry.count)));
+            }
+        }
+        let sk = SecretKey::from_der(&entry.sk)?;
+        if decrypt_string(&sk.to_base64(), &entry.ct, &params)?.as_bytes() != entry.msg.as_slice() {
+            return Err(Error::KnownAnswer(format!("ciphertext of entry {} does not decrypt to its message", entry.count)));
+        }
+    }
+    Ok(entries.len())
+}
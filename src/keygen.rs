@@ -1,6 +1,9 @@
 use polynomial_ring::Polynomial;
 use std::collections::HashMap;
-use crate::utils::{Parameters, add_vec, mul_mat_vec_simple, gen_small_vector, gen_uniform_matrix,compress};
+use ring_lwe::utils::{polyadd, gen_uniform_poly};
+use crate::utils::{Parameters, PolyVec, PolyMatrix, add_vec, mul_poly, mul_vec_simple, mul_mat_vec_simple, gen_small_vector, gen_uniform_matrix, scalar_mul, gadget_levels, SeedStream};
+use crate::homomorphic::KeySwitchingKey;
+use crate::types::{PublicKey, SecretKey};
 
 /// Generate public and secret keys for the ring-LWE cryptosystem
 /// # Arguments
@@ -16,16 +19,41 @@ use crate::utils::{Parameters, add_vec, mul_mat_vec_simple, gen_small_vector, ge
 pub fn keygen(
 	params: &Parameters,
     seed: Option<u64> //random seed
-) -> ((Vec<Vec<Polynomial<i64>>>, Vec<Polynomial<i64>>), Vec<Polynomial<i64>>) {
-    let (n,q,k,f,omega) = (params.n, params.q, params.k, &params.f, params.omega);
+) -> ((PolyMatrix, PolyVec), PolyVec) {
+    let (n,q,k) = (params.n, params.q, params.k);
     //Generate a public and secret key
-    let a = gen_uniform_matrix(n, k, q, seed);
-    let sk = gen_small_vector(n, k, seed);
-    let e = gen_small_vector(n, k, seed);
-    let t = add_vec(&mul_mat_vec_simple(&a, &sk, q, &f, omega), &e, q, &f);
-    
+    let mut seeds = SeedStream::new(seed);
+    let a = gen_uniform_matrix(n, k, q, seeds.next_seed());
+    keygen_with_matrix(&a, params, seeds.next_seed())
+}
+
+/// Generate keys against a given public matrix, so that several key pairs share the same `a`
+/// # Arguments
+/// * `a` - public matrix, e.g. from another key pair
+/// * `params` - Parameters for the ring-LWE cryptosystem
+/// * `seed` - random seed
+/// # Returns
+/// * `((a, t), sk)` - public key (a, t) and secret key (sk)
+/// # Example
+/// ```
+/// let params = module_lwe::utils::Parameters::default();
+/// let (pk0, _) = module_lwe::keygen::keygen(&params, None);
+/// let (pk1, _) = module_lwe::keygen::keygen_with_matrix(&pk0.0, &params, None);
+/// assert_eq!(pk0.0, pk1.0);
+/// ```
+pub fn keygen_with_matrix(
+    a: &[Vec<Polynomial<i64>>],
+    params: &Parameters,
+    seed: Option<u64>
+) -> ((PolyMatrix, PolyVec), PolyVec) {
+    let (n,q,k,f,omega) = (params.n, params.q, params.k, &params.f, params.omega);
+    let mut seeds = SeedStream::new(seed);
+    let sk = gen_small_vector(n, k, seeds.next_seed());
+    let e = gen_small_vector(n, k, seeds.next_seed());
+    let t = add_vec(&mul_mat_vec_simple(a, &sk, q, f, omega), &e, q, f);
+
     //Return public key (a, t) and secret key (sk) as a 2-tuple
-    ((a, t), sk)
+    ((a.to_vec(), t), sk)
 }
 
 /// Generate public and secret keys for the ring-LWE cryptosystem and return them as a HashMap
@@ -44,41 +72,70 @@ pub fn keygen_string(params: &Parameters, seed: Option<u64>) -> HashMap<String,
     // Generate public and secret keys
     let (pk, sk) = keygen(params, seed);
 
-    // Convert the public key to a flattened list of coefficients
-    let mut pk_coeffs: Vec<i64> = pk.0
-        .iter()
-        .flat_map(|row| {
-            row.iter().flat_map(|poly| {
-                let mut coeffs = poly.coeffs().to_vec();
-                coeffs.resize(params.n, 0); // Resize to include leading zeros up to size `n`
-                coeffs
-            })
-        })
-        .collect();
-
-    pk_coeffs.extend(
-        pk.1.iter()
-        .flat_map(|poly| {
-            let mut coeffs = poly.coeffs().to_vec();
-            coeffs.resize(params.n, 0); // Resize to include leading zeros up to size `n`
-            coeffs
-        })
-    );
-
-    // Convert the secret key to a flattened list of coefficients
-    let sk_coeffs: Vec<i64> = sk
-        .iter()
-        .flat_map(|poly| {
-            let mut coeffs = poly.coeffs().to_vec();
-            coeffs.resize(params.n, 0); // Resize to include leading zeros up to size `n`
-            coeffs
-        })
-    .collect();
-
     // Store the Base64 encoded keys in a HashMap
     let mut keys: HashMap<String, String> = HashMap::new();
-    keys.insert(String::from("secret"), compress(&sk_coeffs));
-    keys.insert(String::from("public"), compress(&pk_coeffs));
+    keys.insert(String::from("secret"), SecretKey::new(params, sk).to_base64());
+    keys.insert(String::from("public"), PublicKey::new(params, pk).to_base64());
 
     keys
-}
\ No newline at end of file
+}
+/// Generate a key-switching key encrypting `src_j * base^l` under the secret key `sk`
+/// # Arguments
+/// * `src` - source polynomials
+/// * `sk` - target secret key
+/// * `base` - gadget decomposition base
+/// * `params` - Parameters for the module-LWE cryptosystem
+/// * `seed` - random seed
+/// # Returns
+/// * `ksk` - key-switching key for `homomorphic::key_switch`
+pub fn switching_keygen(
+    src: &[Polynomial<i64>],
+    sk: &[Polynomial<i64>],
+    base: i64,
+    params: &Parameters,
+    seed: Option<u64>
+) -> KeySwitchingKey {
+    let (n, q, k, f, omega) = (params.n, params.q, params.k, &params.f, params.omega);
+    let levels = gadget_levels(q, base);
+    let mut seeds = SeedStream::new(seed);
+    let keys = src.iter()
+        .map(|s| {
+            let mut power = 1i64;
+            (0..levels)
+                .map(|_| {
+                    let a: PolyVec = (0..k).map(|_| gen_uniform_poly(n, q, seeds.next_seed())).collect();
+                    let e = gen_small_vector(n, 1, seeds.next_seed())[0].clone();
+                    let b = polyadd(&polyadd(&mul_vec_simple(sk, &a, q, f, omega), &e, q, f), &scalar_mul(s, power, q), q, f);
+                    power = ((power as i128 * base as i128) % q as i128) as i64;
+                    (a, b)
+                })
+                .collect()
+        })
+        .collect();
+    KeySwitchingKey { base, levels, keys }
+}
+
+/// Generate a relinearization key for homomorphic multiplication
+/// # Arguments
+/// * `sk` - secret key
+/// * `base` - gadget decomposition base
+/// * `params` - Parameters for the module-LWE cryptosystem
+/// * `seed` - random seed
+/// # Returns
+/// * `rlk` - key-switching key from the products sk_i*sk_j (i <= j) to sk
+/// # Example
+/// ```
+/// let params = module_lwe::utils::Parameters::default();
+/// let (pk, sk) = module_lwe::keygen::keygen(&params, None);
+/// let rlk = module_lwe::keygen::relin_keygen(&sk, 1 << 4, &params, None);
+/// ```
+pub fn relin_keygen(sk: &[Polynomial<i64>], base: i64, params: &Parameters, seed: Option<u64>) -> KeySwitchingKey {
+    let (q, k, f, omega) = (params.q, params.k, &params.f, params.omega);
+    let mut products = vec![];
+    for i in 0..k {
+        for j in i..k {
+            products.push(mul_poly(&sk[i], &sk[j], q, f, omega));
+        }
+    }
+    switching_keygen(&products, sk, base, params, seed)
+}
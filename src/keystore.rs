@@ -0,0 +1,159 @@
+use base64::{engine::general_purpose, Engine as _};
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use rand::RngCore;
+use crate::types::SecretKey;
+use crate::pem::{armor_block, unarmor_block};
+use crate::error::Error;
+
+/// Armor label of a passphrase-protected secret key
+const LABEL: &str = "MODULE-LWE ENCRYPTED SECRET KEY";
+/// Bytes of random salt fed to the KDF
+const SALT_LEN: usize = 16;
+/// Bytes of the ChaCha20-Poly1305 nonce
+const NONCE_LEN: usize = 12;
+/// Bytes of the derived wrapping key
+const KEY_LEN: usize = 32;
+/// Largest scrypt cost accepted when decoding, so a hostile file cannot demand unbounded memory
+const MAX_LOG_N: u8 = 20;
+/// Largest scrypt block size and parallelism accepted when decoding
+const MAX_R: u32 = 32;
+const MAX_P: u32 = 16;
+
+/// Cost parameters of the scrypt key derivation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KdfParams {
+    /// log2 of the CPU/memory cost N
+    pub log_n: u8,
+    /// Block size
+    pub r: u32,
+    /// Parallelism
+    pub p: u32,
+}
+
+impl Default for KdfParams {
+    /// N = 2^15, r = 8, p = 1, which takes 32 MiB of memory
+    fn default() -> Self {
+        KdfParams { log_n: 15, r: 8, p: 1 }
+    }
+}
+
+impl KdfParams {
+    fn check(&self) -> Result<(), Error> {
+        if self.log_n == 0 || self.log_n > MAX_LOG_N || self.r == 0 || self.r > MAX_R || self.p == 0 || self.p > MAX_P {
+            return Err(Error::InvalidParameters(format!(
+                "scrypt parameters log_n = {}, r = {}, p = {} are outside 1..={}, 1..={}, 1..={}",
+                self.log_n, self.r, self.p, MAX_LOG_N, MAX_R, MAX_P
+            )));
+        }
+        Ok(())
+    }
+
+    /// Derive the wrapping key from a passphrase with scrypt
+    fn derive(&self, passphrase: &[u8], salt: &[u8]) -> Result<[u8; KEY_LEN], Error> {
+        self.check()?;
+        let params = scrypt::Params::new(self.log_n, self.r, self.p, KEY_LEN)
+            .map_err(|e| Error::InvalidParameters(e.to_string()))?;
+        let mut key = [0u8; KEY_LEN];
+        scrypt::scrypt(passphrase, salt, &params, &mut key).map_err(|e| Error::InvalidParameters(e.to_string()))?;
+        Ok(key)
+    }
+}
+
+fn header<'a>(headers: &'a [(String, String)], name: &str) -> Result<&'a str, Error> {
+    headers.iter()
+        .find(|(n, _)| n == name)
+        .map(|(_, v)| v.as_str())
+        .ok_or_else(|| Error::Decode(format!("missing header {}", name)))
+}
+
+fn parse_header<T: std::str::FromStr>(headers: &[(String, String)], name: &str) -> Result<T, Error> {
+    header(headers, name)?.parse().map_err(|_| Error::Decode(format!("invalid header {}", name)))
+}
+
+fn decode_header<const N: usize>(headers: &[(String, String)], name: &str) -> Result<[u8; N], Error> {
+    general_purpose::STANDARD.decode(header(headers, name)?)
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| Error::Decode(format!("invalid header {}", name)))
+}
+
+impl SecretKey {
+    /// Encrypt the secret key under a passphrase with the default scrypt cost
+    /// # Arguments
+    /// * `passphrase` - passphrase the wrapping key is derived from
+    /// # Returns
+    /// * `pem` - armored `MODULE-LWE ENCRYPTED SECRET KEY` block
+    pub fn to_encrypted_pem(&self, passphrase: &[u8]) -> Result<String, Error> {
+        self.to_encrypted_pem_with(passphrase, &KdfParams::default())
+    }
+
+    /// Encrypt the secret key under a passphrase
+    /// The wrapping key is derived with scrypt from a random salt and seals the DER encoding with ChaCha20-Poly1305
+    /// # Arguments
+    /// * `passphrase` - passphrase the wrapping key is derived from
+    /// * `kdf` - scrypt cost parameters
+    /// # Returns
+    /// * `pem` - armored `MODULE-LWE ENCRYPTED SECRET KEY` block
+    /// # Example
+    /// ```
+    /// use module_lwe::keystore::KdfParams;
+    /// let params = module_lwe::utils::Parameters::builder().n(256).q(7681).k(2).build().unwrap();
+    /// let (_, sk) = module_lwe::keygen::keygen(&params, None);
+    /// let sk = module_lwe::types::SecretKey::new(&params, sk);
+    /// let kdf = KdfParams { log_n: 10, r: 8, p: 1 };
+    /// let pem = sk.to_encrypted_pem_with(b"correct horse", &kdf).unwrap();
+    /// assert_eq!(module_lwe::types::SecretKey::from_encrypted_pem(&pem, b"correct horse").unwrap(), sk);
+    /// assert!(module_lwe::types::SecretKey::from_encrypted_pem(&pem, b"wrong horse").is_err());
+    /// ```
+    pub fn to_encrypted_pem_with(&self, passphrase: &[u8], kdf: &KdfParams) -> Result<String, Error> {
+        let mut salt = [0u8; SALT_LEN];
+        let mut nonce = [0u8; NONCE_LEN];
+        rand::thread_rng().fill_bytes(&mut salt);
+        rand::thread_rng().fill_bytes(&mut nonce);
+        let key = kdf.derive(passphrase, &salt)?;
+        let cipher = ChaCha20Poly1305::new(Key::from_slice(&key));
+        let sealed = cipher.encrypt(Nonce::from_slice(&nonce), Payload { msg: &self.to_der(), aad: LABEL.as_bytes() })
+            .map_err(|_| Error::Authentication)?;
+        let headers = [
+            ("kdf", String::from("scrypt")),
+            ("log-n", kdf.log_n.to_string()),
+            ("r", kdf.r.to_string()),
+            ("p", kdf.p.to_string()),
+            ("salt", general_purpose::STANDARD.encode(salt)),
+            ("nonce", general_purpose::STANDARD.encode(nonce)),
+        ];
+        Ok(armor_block(LABEL, &headers, &sealed))
+    }
+
+    /// Decrypt a secret key produced by `to_encrypted_pem`
+    /// # Arguments
+    /// * `pem` - armored `MODULE-LWE ENCRYPTED SECRET KEY` block
+    /// * `passphrase` - passphrase the key was encrypted under
+    /// # Returns
+    /// * `sk` - the secret key, or `Error::Authentication` if the passphrase is wrong or the block was modified
+    pub fn from_encrypted_pem(pem: &str, passphrase: &[u8]) -> Result<Self, Error> {
+        let (headers, sealed) = unarmor_block(LABEL, pem)?;
+        let kdf_name = header(&headers, "kdf")?;
+        if kdf_name != "scrypt" {
+            return Err(Error::Decode(format!("unsupported kdf {}", kdf_name)));
+        }
+        let kdf = KdfParams {
+            log_n: parse_header(&headers, "log-n")?,
+            r: parse_header(&headers, "r")?,
+            p: parse_header(&headers, "p")?,
+        };
+        let salt: [u8; SALT_LEN] = decode_header(&headers, "salt")?;
+        let nonce: [u8; NONCE_LEN] = decode_header(&headers, "nonce")?;
+        let key = kdf.derive(passphrase, &salt)?;
+        let cipher = ChaCha20Poly1305::new(Key::from_slice(&key));
+        let der = cipher.decrypt(Nonce::from_slice(&nonce), Payload { msg: &sealed, aad: LABEL.as_bytes() })
+            .map_err(|_| Error::Authentication)?;
+        SecretKey::from_der(&der)
+    }
+}
+
+/// Check whether a string holds a passphrase-protected secret key
+pub fn is_encrypted_pem(pem: &str) -> bool {
+    pem.contains(&format!("-----BEGIN {}-----", LABEL))
+}
@@ -1,4 +1,27 @@
 pub mod keygen;
 pub mod encrypt;
 pub mod decrypt;
-pub mod utils;
\ No newline at end of file
+pub mod utils;
+pub mod error;
+pub mod types;
+pub mod pem;
+#[cfg(feature = "keystore")]
+pub mod keystore;
+pub mod homomorphic;
+pub mod noise;
+pub mod security;
+pub mod kat;
+pub mod failure;
+pub mod attacks;
+pub mod cca;
+pub mod export;
+pub mod sign;
+pub mod threshold;
+pub mod dkg;
+pub mod proxy;
+pub mod multi;
+pub mod crs;
+pub mod symmetric;
+pub mod batch;
+pub mod galois;
+mod test;
@@ -1,64 +1,328 @@
-mod keygen;
-mod encrypt;
-mod decrypt;
-mod utils;
-mod test;
-
-use crate::keygen::keygen_string;
-use crate::encrypt::encrypt_string;
-use crate::decrypt::decrypt_string;
-use crate::utils::Parameters;
+use module_lwe::keygen::keygen;
+use module_lwe::encrypt::encrypt_string;
+use module_lwe::decrypt::decrypt_string;
+use module_lwe::utils::Parameters;
+use module_lwe::noise::NoiseDistribution;
+use module_lwe::security::estimate;
+use module_lwe::kat;
+use module_lwe::failure::{measure_failure_rate, FailureRateConfig};
+use module_lwe::attacks::scaling_report;
+use module_lwe::export::LweInstance;
+use module_lwe::types::{PublicKey, SecretKey};
+#[cfg(feature = "keystore")]
+use module_lwe::keystore::is_encrypted_pem;
 use std::env;
-use polynomial_ring::Polynomial;
+
+/// Read a key given inline on the command line or as a path to a file
+fn read_key_arg(arg: &str) -> String {
+    match std::fs::read_to_string(arg) {
+        Ok(contents) => contents,
+        Err(_) => arg.to_string(),
+    }
+}
+
+/// Number of arguments taken up by option flags and their values
+fn flag_args(args: &[String]) -> usize {
+    [("--params", 4), ("--passphrase-file", 2), ("--encrypt-key", 1), ("--threads", 2), ("--csv", 2), ("--block-size", 2)]
+        .iter()
+        .filter(|(flag, _)| args.iter().any(|x| x == flag))
+        .map(|(_, len)| len)
+        .sum()
+}
+
+/// Read the passphrase from the file given by --passphrase-file, or prompt for it on the terminal
+#[cfg(feature = "keystore")]
+fn read_passphrase(args: &[String], prompt: &str) -> Vec<u8> {
+    let passphrase = match args.iter().position(|x| x == "--passphrase-file") {
+        Some(pos) => args.get(pos + 1).and_then(|path| std::fs::read_to_string(path).ok()).map(|contents| {
+            // ignore the trailing newline most editors add
+            contents.trim_end_matches(['\r', '\n']).to_string()
+        }),
+        None => rpassword::prompt_password(prompt).ok(),
+    };
+    match passphrase {
+        Some(passphrase) => passphrase.into_bytes(),
+        None => {
+            eprintln!("could not read passphrase");
+            std::process::exit(1);
+        }
+    }
+}
 
 fn main() {
     let args: Vec<String> = env::args().collect();
 
     // Initialize struct with default values
     let mut params = Parameters::default();
-    // Check for --params flag and get the updated values directly
+    // Check for --params flag and rebuild validated parameters from it
     if let Some(pos) = args.iter().position(|x| x == "--params") {
         if args.len() > pos + 3 {
-            params.n = args.get(pos + 1).and_then(|s| s.parse().ok()).unwrap_or(params.n);
-            params.q = args.get(pos + 2).and_then(|s| s.parse().ok()).unwrap_or(params.q);
-            params.k = args.get(pos + 3).and_then(|s| s.parse().ok()).unwrap_or(params.k);
-            let mut poly_vec = vec![0i64;params.n+1];
-            poly_vec[0] = 1;
-            poly_vec[params.n] = 1;
-            params.f = Polynomial::new(poly_vec);
+            let builder = Parameters::builder()
+                .n(args.get(pos + 1).and_then(|s| s.parse().ok()).unwrap_or(params.n()))
+                .q(args.get(pos + 2).and_then(|s| s.parse().ok()).unwrap_or(params.q()))
+                .k(args.get(pos + 3).and_then(|s| s.parse().ok()).unwrap_or(params.k()));
+            params = match builder.build() {
+                Ok(params) => params,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+            };
         }
     }
 
     let method = if args.len() > 1 {&args[1]} else {""};
 
     if method == "keygen" {
-        if args.len() != 2 && args.len() != 6 {
-            println!("Usage: cargo run -- keygen");
+        if args.len().saturating_sub(flag_args(&args)) != 2 {
+            println!("Usage: cargo run -- keygen [--encrypt-key [--passphrase-file <path>]]");
             return;
         }
-        let keypair = keygen_string(&params,None);
-        println!("{:?}", keypair);
+        let (pk, sk) = keygen(&params, None);
+        print!("{}", PublicKey::new(&params, pk).to_pem());
+        let sk = SecretKey::new(&params, sk);
+        if args.iter().any(|x| x == "--encrypt-key") {
+            #[cfg(feature = "keystore")]
+            match sk.to_encrypted_pem(&read_passphrase(&args, "Passphrase: ")) {
+                Ok(pem) => print!("{}", pem),
+                Err(e) => {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+            }
+            #[cfg(not(feature = "keystore"))]
+            {
+                eprintln!("--encrypt-key requires the keystore feature");
+                std::process::exit(1);
+            }
+        } else {
+            print!("{}", sk.to_pem());
+        }
     }
 
     if method == "encrypt" {
-        if args.len() != 4 && args.len() != 8 {
+        if args.len().saturating_sub(flag_args(&args)) != 4 {
             println!("Usage: cargo run -- encrypt <public_key> <message_string>");
             return;
         }
-        let pk_string = &args[2];
+        // Accept an armored public key, inline or as a file, or the legacy base64 encoding
+        let pk_arg = read_key_arg(&args[2]);
+        let (pk_string, params) = if pk_arg.contains("-----BEGIN") {
+            match PublicKey::from_pem(&pk_arg) {
+                Ok(pk) => (pk.to_base64(), pk.params),
+                Err(e) => {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+            }
+        } else {
+            (pk_arg, params.clone())
+        };
         let message_string = &args[3];
-        let ciphertext_string = encrypt_string(pk_string,message_string,&params,None);
-        println!("{}",ciphertext_string);
+        match encrypt_string(&pk_string,message_string,&params,None) {
+            Ok(ciphertext_string) => println!("{}",ciphertext_string),
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
     }
 
     if method == "decrypt" {
-        if args.len() != 4 && args.len() != 8 {
-            println!("Usage: cargo run -- decrypt <secret_key> <ciphertext>");
+        if args.len().saturating_sub(flag_args(&args)) != 4 {
+            println!("Usage: cargo run -- decrypt <secret_key> <ciphertext> [--passphrase-file <path>]");
             return;
         }
-        let sk_string = &args[2];
+        // Accept an armored secret key, inline or as a file, or the legacy base64 encoding
+        let sk_arg = read_key_arg(&args[2]);
+        let (sk_string, params) = if sk_arg.contains("-----BEGIN") {
+            #[cfg(feature = "keystore")]
+            let sk = if is_encrypted_pem(&sk_arg) {
+                SecretKey::from_encrypted_pem(&sk_arg, &read_passphrase(&args, "Passphrase: "))
+            } else {
+                SecretKey::from_pem(&sk_arg)
+            };
+            #[cfg(not(feature = "keystore"))]
+            let sk = SecretKey::from_pem(&sk_arg);
+            match sk {
+                Ok(sk) => (sk.to_base64(), sk.params),
+                Err(e) => {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+            }
+        } else {
+            (sk_arg, params.clone())
+        };
         let ciphertext_string = &args[3];
-        let plaintext_message = decrypt_string(sk_string,ciphertext_string,&params);
-        println!("{}",plaintext_message);
+        match decrypt_string(&sk_string,ciphertext_string,&params) {
+            Ok(plaintext_message) => println!("{}",plaintext_message),
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    if method == "security" {
+        let estimate = estimate(&params, &NoiseDistribution::Ternary, &NoiseDistribution::Ternary);
+        for (name, attack) in [("primal", estimate.primal), ("dual", estimate.dual)] {
+            println!("{}: beta = {}, samples = {}, classical = {:.1} bits, quantum = {:.1} bits",
+                name, attack.beta, attack.samples, attack.classical_bits, attack.quantum_bits);
+        }
+        println!("security: {:.1} bits classical, {:.1} bits quantum", estimate.classical_bits(), estimate.quantum_bits());
+
+        // Check for --min-bits flag and reject weaker parameters
+        if let Some(pos) = args.iter().position(|x| x == "--min-bits") {
+            let min_bits: f64 = match args.get(pos + 1).and_then(|s| s.parse().ok()) {
+                Some(b) => b,
+                None => {
+                    println!("Usage: cargo run -- security [--min-bits <bits>]");
+                    return;
+                }
+            };
+            if estimate.classical_bits() < min_bits {
+                eprintln!("rejected: {:.1} bits is below the required {} bits", estimate.classical_bits(), min_bits);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    if method == "kat" {
+        let usage = "Usage: cargo run -- kat generate <preset> <entries> | cargo run -- kat verify <file>";
+        match (args.get(2).map(String::as_str), args.len()) {
+            (Some("generate"), 5) => {
+                let params = match Parameters::preset(&args[3]) {
+                    Some(params) => params,
+                    None => {
+                        eprintln!("unknown preset {}", args[3]);
+                        std::process::exit(1);
+                    }
+                };
+                match args[4].parse() {
+                    Ok(entries) => print!("{}", kat::generate(&args[3], &params, entries)),
+                    Err(_) => println!("{}", usage),
+                }
+            }
+            (Some("verify"), 4) => {
+                let contents = match std::fs::read_to_string(&args[3]) {
+                    Ok(contents) => contents,
+                    Err(e) => {
+                        eprintln!("{}: {}", args[3], e);
+                        std::process::exit(1);
+                    }
+                };
+                match kat::verify(&contents) {
+                    Ok(entries) => println!("{} entries verified", entries),
+                    Err(e) => {
+                        eprintln!("{}", e);
+                        std::process::exit(1);
+                    }
+                }
+            }
+            _ => println!("{}", usage),
+        }
+    }
+
+    if method == "failure-rate" {
+        let usage = "Usage: cargo run --release -- failure-rate <trials> [--threads <threads>] [--csv <prefix>]";
+        let trials = match args.get(2).and_then(|s| s.parse().ok()) {
+            Some(trials) if args.len().saturating_sub(flag_args(&args)) == 3 => trials,
+            _ => {
+                println!("{}", usage);
+                return;
+            }
+        };
+        let mut config = FailureRateConfig { trials, ..FailureRateConfig::default() };
+        if let Some(pos) = args.iter().position(|x| x == "--threads") {
+            match args.get(pos + 1).and_then(|s| s.parse().ok()) {
+                Some(threads) => config.threads = threads,
+                None => {
+                    println!("{}", usage);
+                    return;
+                }
+            }
+        }
+        let report = measure_failure_rate(&params, &config);
+        println!("trials: {}", report.trials);
+        println!("failed ciphertexts: {} (rate {:.3e})", report.failures, report.failure_rate());
+        println!("failed coefficients: {} (rate {:.3e})", report.coefficient_failures.iter().sum::<u64>(), report.coefficient_failure_rate());
+        println!("noise variance: {:.2} measured, {:.2} analytic", report.empirical_variance(), report.analytic_variance);
+        println!("largest noise: {}", report.histogram.keys().map(|e| e.abs()).max().unwrap_or(0));
+        println!("analytic failure rate: {:.3e} (2^{:.1})", report.analytic_failure_rate(), report.analytic_log2);
+
+        // Check for --csv flag and export the histogram and per-coefficient counts
+        if let Some(pos) = args.iter().position(|x| x == "--csv") {
+            let prefix = match args.get(pos + 1) {
+                Some(prefix) => prefix,
+                None => {
+                    println!("{}", usage);
+                    return;
+                }
+            };
+            for (suffix, contents) in [("histogram", report.histogram_csv()), ("coefficients", report.coefficients_csv())] {
+                let path = format!("{}_{}.csv", prefix, suffix);
+                if let Err(e) = std::fs::write(&path, contents) {
+                    eprintln!("{}: {}", path, e);
+                    std::process::exit(1);
+                }
+                println!("wrote {}", path);
+            }
+        }
+    }
+
+    if method == "attack" {
+        let usage = "Usage: cargo run --release -- attack --params <n> <q> <k> [--block-size <b>]";
+        // the default parameters are far beyond reach, so the target must be chosen explicitly
+        if !args.iter().any(|x| x == "--params") || args.len().saturating_sub(flag_args(&args)) != 2 {
+            println!("{}", usage);
+            return;
+        }
+        let mut block_size = 10;
+        if let Some(pos) = args.iter().position(|x| x == "--block-size") {
+            match args.get(pos + 1).and_then(|s| s.parse().ok()) {
+                Some(b) if b >= 2 => block_size = b,
+                _ => {
+                    println!("{}", usage);
+                    return;
+                }
+            }
+        }
+        // Attack every module rank up to k to show how the cost grows with the lattice dimension
+        let ranks: Vec<usize> = (1..=params.k()).collect();
+        let timings = match scaling_report(params.n(), params.q(), &ranks, block_size) {
+            Ok(timings) => timings,
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        };
+        println!("primal attack with BKZ-{} on n = {}, q = {}", block_size, params.n(), params.q());
+        for timing in timings {
+            println!("k = {}: dimension {}, {:.3} s, secret key {}",
+                timing.k, timing.dimension, timing.elapsed.as_secs_f64(),
+                if timing.recovered { "recovered" } else { "not recovered" });
+        }
+    }
+
+    if method == "export" {
+        if args.len().saturating_sub(flag_args(&args)) != 3 {
+            println!("Usage: cargo run -- export <directory> [--params <n> <q> <k>]");
+            return;
+        }
+        // Export a fresh key pair, keeping the secret so that solutions can be checked
+        let (pk, sk) = keygen(&params, None);
+        let instance = LweInstance::new(&pk, Some(&sk), &params);
+        match instance.write(std::path::Path::new(&args[2])) {
+            Ok(paths) => {
+                for path in paths {
+                    println!("wrote {}", path.display());
+                }
+            }
+            Err(e) => {
+                eprintln!("{}: {}", args[2], e);
+                std::process::exit(1);
+            }
+        }
     }
-}
\ No newline at end of file
+}
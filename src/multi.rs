@@ -0,0 +1,115 @@
+use polynomial_ring::Polynomial;
+use ring_lwe::utils::{polyadd, polysub, nearest_int};
+use crate::utils::{Parameters, PolyMatrix, PolyVec, add_vec, mul_vec_simple, mul_mat_vec_simple, transpose, gen_small_vector, SeedStream};
+use crate::decrypt::decode;
+use crate::types::{check_len, check_vec, check_poly};
+use crate::error::Error;
+
+/// Ciphertext of one message for several recipients that share the public matrix
+/// Every recipient's ciphertext is (u, v[i]), so the common part u is sent only once
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MultiCiphertext {
+    /// Ciphertext vector a^T*r + e1, shared by all recipients
+    pub u: PolyVec,
+    /// One ciphertext polynomial t_i*r + e2_i - m per recipient, in the order of the public keys
+    pub v: PolyVec,
+}
+
+impl MultiCiphertext {
+    /// Number of recipients
+    pub fn len(&self) -> usize {
+        self.v.len()
+    }
+
+    /// Whether the ciphertext has no recipients
+    pub fn is_empty(&self) -> bool {
+        self.v.is_empty()
+    }
+
+    /// The ordinary ciphertext (u, v) of one recipient, for `decrypt::decrypt`
+    pub fn recipient(&self, index: usize) -> Option<(PolyVec, Polynomial<i64>)> {
+        self.v.get(index).map(|v| (self.u.clone(), v.clone()))
+    }
+}
+
+/// Encrypt a message to several public keys at once, reusing the ephemeral vector r and the noise e1
+/// All keys must share the matrix `a`, e.g. from `keygen::keygen_with_matrix`; each recipient gets fresh noise e2,
+/// so every (u, v[i]) is distributed as an ordinary encryption under its key
+/// # Arguments
+/// * `pks` - public keys (a, t_i) of the recipients
+/// * `m_b` - binary message
+/// * `params` - Parameters for the module-LWE cryptosystem
+/// * `seed` - random seed
+/// # Returns
+/// * `ct` - multi-recipient ciphertext, or an error if there are no keys, a key is malformed or the matrices differ
+/// # Example
+/// ```
+/// use module_lwe::multi::{encrypt_multi, decrypt_multi};
+/// use module_lwe::keygen::{keygen, keygen_with_matrix};
+/// let params = module_lwe::utils::Parameters::default();
+/// let (pk0, sk0) = keygen(&params, None);
+/// let (pk1, sk1) = keygen_with_matrix(&pk0.0, &params, None);
+/// let mut m_b = vec![1, 0, 1, 1];
+/// m_b.resize(params.n(), 0);
+/// let ct = encrypt_multi(&[pk0, pk1], &m_b, &params, None).unwrap();
+/// assert_eq!(decrypt_multi(&sk0, &ct, 0, &params).unwrap(), m_b);
+/// assert_eq!(decrypt_multi(&sk1, &ct, 1, &params).unwrap(), m_b);
+/// ```
+pub fn encrypt_multi(
+    pks: &[(PolyMatrix, PolyVec)],
+    m_b: &[i64],
+    params: &Parameters,
+    seed: Option<u64>
+) -> Result<MultiCiphertext, Error> {
+    let (n, q, k, f, omega) = (params.n, params.q, params.k, &params.f, params.omega);
+    let a = match pks.first() {
+        Some(pk) => &pk.0,
+        None => return Err(Error::InvalidParameters(String::from("no public keys to encrypt to"))),
+    };
+    check_len("a", a.len(), k)?;
+    for row in a.iter() {
+        check_vec(row, params)?;
+    }
+    for (i, pk) in pks.iter().enumerate() {
+        if pk.0 != *a {
+            return Err(Error::InvalidParameters(format!("public key {} does not share the matrix of public key 0", i)));
+        }
+        check_vec(&pk.1, params)?;
+    }
+    if m_b.len() > n {
+        return Err(Error::InvalidParameters(format!("message has {} bits, at most n = {} fit in one ciphertext", m_b.len(), n)));
+    }
+    let mut seeds = SeedStream::new(seed);
+    let r = gen_small_vector(n, k, seeds.next_seed());
+    let e1 = gen_small_vector(n, k, seeds.next_seed());
+    let half_q = nearest_int(q, 2);
+    let m = Polynomial::new(vec![half_q]) * Polynomial::new(m_b.to_vec());
+    let u = add_vec(&mul_mat_vec_simple(&transpose(a), &r, q, f, omega), &e1, q, f);
+    let v = pks.iter()
+        .map(|pk| {
+            let e2 = gen_small_vector(n, 1, seeds.next_seed()).remove(0);
+            polysub(&polyadd(&mul_vec_simple(&pk.1, &r, q, f, omega), &e2, q, f), &m, q, f)
+        })
+        .collect();
+    Ok(MultiCiphertext { u, v })
+}
+
+/// Decrypt one recipient's share of a multi-recipient ciphertext
+/// # Arguments
+/// * `sk` - the recipient's secret key
+/// * `ct` - multi-recipient ciphertext
+/// * `index` - position of the recipient's public key in the list passed to `encrypt_multi`
+/// * `params` - Parameters for the module-LWE cryptosystem
+/// # Returns
+/// * `decrypted_coeffs` - plaintext vector of length n, or an error if the index is out of range or the input is malformed
+pub fn decrypt_multi(sk: &[Polynomial<i64>], ct: &MultiCiphertext, index: usize, params: &Parameters) -> Result<Vec<i64>, Error> {
+    let (n, q, f, omega) = (params.n, params.q, &params.f, params.omega);
+    let v = ct.v.get(index)
+        .ok_or_else(|| Error::InvalidParameters(format!("recipient {} out of range for {} recipients", index, ct.len())))?;
+    check_vec(sk, params)?;
+    check_vec(&ct.u, params)?;
+    check_poly(v, params)?;
+    let mut decrypted_coeffs = decode(&polysub(v, &mul_vec_simple(sk, &ct.u, q, f, omega), q, f), q);
+    decrypted_coeffs.resize(n, 0);
+    Ok(decrypted_coeffs)
+}
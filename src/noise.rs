@@ -0,0 +1,205 @@
+use polynomial_ring::Polynomial;
+use ring_lwe::utils::{polysub, nearest_int};
+use crate::utils::{Parameters, mul_vec_simple};
+use crate::decrypt::decrypt;
+
+/// Distribution of the secret and error coefficients
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum NoiseDistribution {
+    /// Uniform on {-1, 0, 1}, as sampled by `utils::gen_small_vector`
+    #[default]
+    Ternary,
+    /// Centered binomial distribution with parameter eta
+    CenteredBinomial(u32),
+    /// Rounded Gaussian with standard deviation sigma
+    Gaussian(f64),
+}
+
+impl NoiseDistribution {
+    /// Variance of a single coefficient
+    pub fn variance(&self) -> f64 {
+        match *self {
+            NoiseDistribution::Ternary => 2.0 / 3.0,
+            NoiseDistribution::CenteredBinomial(eta) => eta as f64 / 2.0,
+            NoiseDistribution::Gaussian(sigma) => sigma * sigma,
+        }
+    }
+}
+
+/// Natural logarithm of the complementary error function
+/// Chebyshev fit from Numerical Recipes (fractional error below 1.2e-7), evaluated in log space so it never underflows
+fn ln_erfc(x: f64) -> f64 {
+    if x < 0.0 {
+        return (2.0 - ln_erfc(-x).exp()).ln();
+    }
+    let t = 1.0 / (1.0 + 0.5 * x);
+    let poly = -1.26551223 + t * (1.00002368 + t * (0.37409196 + t * (0.09678418
+        + t * (-0.18628806 + t * (0.27886807 + t * (-1.13520398 + t * (1.48851587
+        + t * (-0.82215223 + t * 0.17087277))))))));
+    t.ln() - x * x + poly
+}
+
+/// Smallest noise magnitude that can flip a decrypted bit
+fn failure_threshold(q: i64) -> i64 {
+    let half_q = nearest_int(q, 2);
+    half_q - half_q / 2
+}
+
+/// Variance of each coefficient of the decryption noise `e*r - sk*e1 + e2` of a fresh ciphertext
+/// # Arguments
+/// * `params` - Parameters for the module-LWE cryptosystem
+/// * `secret` - distribution of `sk` and `r`
+/// * `error` - distribution of `e`, `e1` and `e2`
+/// # Returns
+/// * `variance` - variance of a single noise coefficient
+pub fn noise_variance(params: &Parameters, secret: &NoiseDistribution, error: &NoiseDistribution) -> f64 {
+    let (n, k) = (params.n as f64, params.k as f64);
+    2.0 * k * n * secret.variance() * error.variance() + error.variance()
+}
+
+/// Base-2 logarithm of the probability that some coefficient fails to decrypt
+/// Uses a Gaussian tail for each coefficient and a union bound over the n coefficients
+/// # Arguments
+/// * `params` - Parameters for the module-LWE cryptosystem
+/// * `variance` - variance of each noise coefficient, e.g. `j * noise_variance(..)` after summing j ciphertexts
+/// # Returns
+/// * `log2_p` - log2 of the decryption failure probability
+pub fn failure_probability_log2(params: &Parameters, variance: f64) -> f64 {
+    if variance <= 0.0 {
+        return f64::NEG_INFINITY;
+    }
+    // continuity correction: the noise is integral, so |e| >= threshold means |e| > threshold - 1/2
+    let x = (failure_threshold(params.q) as f64 - 0.5) / (2.0 * variance).sqrt();
+    let log2_coeff = ln_erfc(x) / std::f64::consts::LN_2;
+    (log2_coeff + (params.n as f64).log2()).min(0.0)
+}
+
+/// Number of times a fresh ciphertext can be passed through `homomorphic::rerandomize`
+/// Each rerandomization adds an independent fresh noise term, so after j of them the variance is (j + 1) times `noise_variance`
+/// # Arguments
+/// * `params` - Parameters for the module-LWE cryptosystem
+/// * `secret` - distribution of `sk` and `r`
+/// * `error` - distribution of `e`, `e1` and `e2`
+/// * `log2_target` - largest acceptable log2 decryption failure probability, e.g. -128
+/// # Returns
+/// * `j` - largest number of rerandomizations that keeps the failure probability at or below the target,
+///   or `None` if even a fresh ciphertext misses it or the target is not negative, which every count meets
+/// # Example
+/// ```
+/// use module_lwe::noise::{max_rerandomizations, NoiseDistribution};
+/// let params = module_lwe::utils::Parameters::default();
+/// let j = max_rerandomizations(&params, &NoiseDistribution::Ternary, &NoiseDistribution::Ternary, -128.0);
+/// assert_eq!(j, Some(13));
+/// ```
+pub fn max_rerandomizations(
+    params: &Parameters,
+    secret: &NoiseDistribution,
+    error: &NoiseDistribution,
+    log2_target: f64
+) -> Option<usize> {
+    let variance = noise_variance(params, secret, error);
+    if log2_target >= 0.0 || failure_probability_log2(params, variance) > log2_target {
+        return None;
+    }
+    if variance <= 0.0 {
+        return Some(usize::MAX);
+    }
+    // the failure probability grows with the number of fresh noise terms c = j + 1: double c until it misses the
+    // target, then binary search between the last count that meets it and the first that does not
+    let meets = |c: usize| failure_probability_log2(params, c as f64 * variance) <= log2_target;
+    let (mut lo, mut hi) = (1usize, 2usize);
+    while meets(hi) {
+        if hi > usize::MAX / 2 {
+            return Some(usize::MAX);
+        }
+        lo = hi;
+        hi *= 2;
+    }
+    while hi - lo > 1 {
+        let mid = lo + (hi - lo) / 2;
+        if meets(mid) {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+    Some(lo - 1)
+}
+
+/// Analytic decryption failure probability of `encrypt`/`decrypt`
+/// # Arguments
+/// * `params` - Parameters for the module-LWE cryptosystem
+/// * `secret` - distribution of `sk` and `r`
+/// * `error` - distribution of `e`, `e1` and `e2`
+/// # Returns
+/// * `p` - probability that a fresh ciphertext decrypts incorrectly
+/// # Example
+/// ```
+/// use module_lwe::noise::{failure_probability, NoiseDistribution};
+/// let params = module_lwe::utils::Parameters::default();
+/// let p = failure_probability(&params, &NoiseDistribution::Ternary, &NoiseDistribution::Ternary);
+/// assert!(p < 1e-100);
+/// ```
+pub fn failure_probability(params: &Parameters, secret: &NoiseDistribution, error: &NoiseDistribution) -> f64 {
+    failure_probability_log2(params, noise_variance(params, secret, error)).exp2()
+}
+
+/// Measure the noise of a ciphertext with the secret key
+/// Encryption computes `v = t*r + e2 - m*half_q`, so the noise is `v - sk*u + m*half_q` mod q
+/// # Arguments
+/// * `sk` - secret key
+/// * `u` - ciphertext vector
+/// * `v` - ciphertext polynomial
+/// * `m_b` - binary message the ciphertext encrypts
+/// * `params` - Parameters for the module-LWE cryptosystem
+/// # Returns
+/// * `noise` - centered noise coefficients, padded to length n
+/// # Example
+/// ```
+/// let params = module_lwe::utils::Parameters::default();
+/// let (pk, sk) = module_lwe::keygen::keygen(&params, None);
+/// let m_b = vec![1, 0, 1];
+/// let (u, v) = module_lwe::encrypt::encrypt(&pk.0, &pk.1, &m_b, &params, None);
+/// let noise = module_lwe::noise::measure_noise(&sk, &u, &v, &m_b, &params);
+/// assert!(noise.iter().all(|e| e.abs() < params.q() / 4));
+/// ```
+pub fn measure_noise(
+    sk: &[Polynomial<i64>],
+    u: &[Polynomial<i64>],
+    v: &Polynomial<i64>,
+    m_b: &[i64],
+    params: &Parameters
+) -> Vec<i64> {
+    let (n, q, f, omega) = (params.n, params.q, &params.f, params.omega);
+    let half_q = nearest_int(q, 2);
+    let mut phase = polysub(v, &mul_vec_simple(sk, u, q, f, omega), q, f).coeffs().to_vec();
+    phase.resize(n, 0);
+    phase.iter()
+        .enumerate()
+        .map(|(i, &c)| {
+            let m = m_b.get(i).copied().unwrap_or(0).rem_euclid(2);
+            let e = (c + m * half_q).rem_euclid(q);
+            if e > q / 2 { e - q } else { e }
+        })
+        .collect()
+}
+
+/// Remaining noise budget of a ciphertext in bits
+/// The message is recovered with `decrypt`, so the budget is only meaningful while the ciphertext still decrypts correctly
+/// # Arguments
+/// * `sk` - secret key
+/// * `u` - ciphertext vector
+/// * `v` - ciphertext polynomial
+/// * `params` - Parameters for the module-LWE cryptosystem
+/// # Returns
+/// * `bits` - log2 of the failure threshold (about q/4) over the largest noise coefficient
+pub fn noise_budget(sk: &[Polynomial<i64>], u: &[Polynomial<i64>], v: &Polynomial<i64>, params: &Parameters) -> f64 {
+    let m_b = decrypt(sk, u, v, params);
+    let max_noise = measure_noise(sk, u, v, &m_b, params)
+        .iter()
+        .map(|e| e.abs())
+        .max()
+        .unwrap_or(0)
+        .max(1);
+    (failure_threshold(params.q) as f64 / max_noise as f64).log2()
+}
@@ -0,0 +1,371 @@
+use polynomial_ring::Polynomial;
+use base64::{engine::general_purpose, Engine as _};
+use crate::utils::{Parameters, PolyVec};
+use crate::types::{PublicKey, SecretKey, Ciphertext, to_canonical, from_canonical};
+use crate::error::Error;
+
+/// DER tag of a SEQUENCE
+const TAG_SEQUENCE: u8 = 0x30;
+/// DER tag of an INTEGER
+const TAG_INTEGER: u8 = 0x02;
+/// DER tag of an OCTET STRING
+const TAG_OCTET_STRING: u8 = 0x04;
+/// Line width of the base64 body of an armored block
+const PEM_LINE_WIDTH: usize = 64;
+
+/// Name/value headers of an armored block
+pub(crate) type Headers = Vec<(String, String)>;
+
+/// Kind of object stored in an encoding, with its armor label
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    PublicKey = 0,
+    SecretKey = 1,
+    Ciphertext = 2,
+}
+
+impl Kind {
+    fn label(&self) -> &'static str {
+        match self {
+            Kind::PublicKey => "MODULE-LWE PUBLIC KEY",
+            Kind::SecretKey => "MODULE-LWE SECRET KEY",
+            Kind::Ciphertext => "MODULE-LWE CIPHERTEXT",
+        }
+    }
+}
+
+fn write_length(out: &mut Vec<u8>, len: usize) {
+    if len < 0x80 {
+        out.push(len as u8);
+    } else {
+        let bytes: Vec<u8> = len.to_be_bytes().iter().copied().skip_while(|&b| b == 0).collect();
+        out.push(0x80 | bytes.len() as u8);
+        out.extend(bytes);
+    }
+}
+
+fn write_tlv(out: &mut Vec<u8>, tag: u8, value: &[u8]) {
+    out.push(tag);
+    write_length(out, value.len());
+    out.extend_from_slice(value);
+}
+
+fn write_integer(out: &mut Vec<u8>, x: u64) {
+    let mut bytes: Vec<u8> = x.to_be_bytes().iter().copied().skip_while(|&b| b == 0).collect();
+    // non-negative integers need a leading zero when the high bit is set
+    if bytes.first().is_none_or(|&b| b & 0x80 != 0) {
+        bytes.insert(0, 0);
+    }
+    write_tlv(out, TAG_INTEGER, &bytes);
+}
+
+/// Reader over DER-encoded bytes that returns errors instead of panicking on truncated input
+struct DerReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> DerReader<'a> {
+    fn read_tlv(&mut self, tag: u8) -> Result<&'a [u8], Error> {
+        let (&found, rest) = self.data.split_first().ok_or_else(|| Error::Decode(String::from("unexpected end of data")))?;
+        if found != tag {
+            return Err(Error::Decode(format!("expected tag {:#04x}, found {:#04x}", tag, found)));
+        }
+        let (&first, mut rest) = rest.split_first().ok_or_else(|| Error::Decode(String::from("missing length")))?;
+        let len = if first < 0x80 {
+            first as usize
+        } else {
+            let count = (first & 0x7f) as usize;
+            if count == 0 || count > std::mem::size_of::<usize>() || count > rest.len() {
+                return Err(Error::Decode(String::from("invalid length")));
+            }
+            let len = rest[..count].iter().fold(0usize, |acc, &b| (acc << 8) | b as usize);
+            if len < 0x80 || rest[0] == 0 {
+                return Err(Error::Decode(String::from("non-minimal length")));
+            }
+            rest = &rest[count..];
+            len
+        };
+        if len > rest.len() {
+            return Err(Error::Decode(String::from("length exceeds data")));
+        }
+        let (value, rest) = rest.split_at(len);
+        self.data = rest;
+        Ok(value)
+    }
+
+    fn read_integer(&mut self) -> Result<u64, Error> {
+        let bytes = self.read_tlv(TAG_INTEGER)?;
+        let minimal = match bytes {
+            [] => false,
+            [0, next, ..] => next & 0x80 != 0,
+            [first, ..] => first & 0x80 == 0,
+        };
+        if !minimal || bytes.len() > 9 || (bytes.len() == 9 && bytes[0] != 0) {
+            return Err(Error::Decode(String::from("invalid integer")));
+        }
+        Ok(bytes.iter().fold(0u64, |acc, &b| (acc << 8) | b as u64))
+    }
+}
+
+/// Bytes needed for a coefficient in [0, q)
+fn coeff_width(q: i64) -> usize {
+    let bits = 64 - ((q - 1) as u64).leading_zeros() as usize;
+    bits.div_ceil(8).max(1)
+}
+
+/// SEQUENCE { kind INTEGER, n INTEGER, q INTEGER, k INTEGER, coefficients OCTET STRING }
+fn encode_der(kind: Kind, params: &Parameters, polys: &[&Polynomial<i64>]) -> Vec<u8> {
+    let width = coeff_width(params.q);
+    let mut packed = Vec::with_capacity(polys.len() * params.n * width);
+    for p in polys {
+        for c in to_canonical(p, params) {
+            packed.extend_from_slice(&(c as u64).to_le_bytes()[..width]);
+        }
+    }
+    let mut body = vec![];
+    write_integer(&mut body, kind as u64);
+    write_integer(&mut body, params.n as u64);
+    write_integer(&mut body, params.q as u64);
+    write_integer(&mut body, params.k as u64);
+    write_tlv(&mut body, TAG_OCTET_STRING, &packed);
+    let mut out = vec![];
+    write_tlv(&mut out, TAG_SEQUENCE, &body);
+    out
+}
+
+/// Inverse of `encode_der`, returning the parameters and `count(k)` polynomials
+fn decode_der(kind: Kind, der: &[u8], count: fn(usize) -> usize) -> Result<(Parameters, PolyVec), Error> {
+    let mut outer = DerReader { data: der };
+    let body = outer.read_tlv(TAG_SEQUENCE)?;
+    if !outer.data.is_empty() {
+        return Err(Error::Decode(String::from("trailing data after sequence")));
+    }
+    let mut reader = DerReader { data: body };
+    let found = reader.read_integer()?;
+    if found != kind as u64 {
+        return Err(Error::Decode(format!("expected a {}, found kind {}", kind.label(), found)));
+    }
+    let (n, q, k) = (reader.read_integer()?, reader.read_integer()?, reader.read_integer()?);
+    let params = Parameters::builder()
+        .n(usize::try_from(n).map_err(|_| Error::Decode(String::from("n is too large")))?)
+        .q(i64::try_from(q).map_err(|_| Error::Decode(String::from("q is too large")))?)
+        .k(usize::try_from(k).map_err(|_| Error::Decode(String::from("k is too large")))?)
+        .build()?;
+    let packed = reader.read_tlv(TAG_OCTET_STRING)?;
+    if !reader.data.is_empty() {
+        return Err(Error::Decode(String::from("trailing data in sequence")));
+    }
+    let width = coeff_width(params.q);
+    // every polynomial takes at least one byte, which bounds k before computing the expected size
+    if params.k > packed.len() {
+        return Err(Error::Decode(format!("k = {} is too large for {} coefficient bytes", params.k, packed.len())));
+    }
+    let expected = count(params.k)
+        .checked_mul(params.n)
+        .and_then(|c| c.checked_mul(width))
+        .ok_or_else(|| Error::Decode(String::from("dimensions are too large")))?;
+    if packed.len() != expected {
+        return Err(Error::Decode(format!("expected {} coefficient bytes, found {}", expected, packed.len())));
+    }
+    let coeffs: Vec<i64> = packed.chunks(width)
+        .map(|chunk| {
+            let mut bytes = [0u8; 8];
+            bytes[..width].copy_from_slice(chunk);
+            u64::from_le_bytes(bytes) as i64
+        })
+        .collect();
+    let polys = coeffs.chunks(params.n)
+        .map(|c| from_canonical(c.to_vec(), &params))
+        .collect::<Result<PolyVec, Error>>()?;
+    Ok((params, polys))
+}
+
+/// Wrap binary data in an armored block with the given label and headers
+pub(crate) fn armor_block(label: &str, headers: &[(&str, String)], data: &[u8]) -> String {
+    let encoded = general_purpose::STANDARD.encode(data);
+    let mut out = format!("-----BEGIN {}-----\n", label);
+    for (name, value) in headers {
+        out.push_str(&format!("{}: {}\n", name, value));
+    }
+    if !headers.is_empty() {
+        out.push('\n');
+    }
+    for line in encoded.as_bytes().chunks(PEM_LINE_WIDTH) {
+        out.push_str(std::str::from_utf8(line).expect("base64 is ascii"));
+        out.push('\n');
+    }
+    out.push_str(&format!("-----END {}-----\n", label));
+    out
+}
+
+/// Split an armored block with the given label into its headers and decoded binary data
+pub(crate) fn unarmor_block(label: &str, pem: &str) -> Result<(Headers, Vec<u8>), Error> {
+    let begin = format!("-----BEGIN {}-----", label);
+    let end = format!("-----END {}-----", label);
+    let mut lines = pem.lines().map(str::trim).skip_while(|line| line.is_empty());
+    if lines.next() != Some(begin.as_str()) {
+        return Err(Error::Decode(format!("missing {}", begin)));
+    }
+    let mut headers = vec![];
+    let mut body = String::new();
+    let mut in_headers = true;
+    let mut terminated = false;
+    for line in lines.by_ref() {
+        if line == end {
+            terminated = true;
+            break;
+        }
+        if in_headers {
+            if line.is_empty() {
+                in_headers = false;
+                continue;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                headers.push((name.trim().to_string(), value.trim().to_string()));
+                continue;
+            }
+            in_headers = false;
+        }
+        body.push_str(line);
+    }
+    if !terminated {
+        return Err(Error::Decode(format!("missing {}", end)));
+    }
+    if lines.any(|line| !line.is_empty()) {
+        return Err(Error::Decode(String::from("trailing data after armored block")));
+    }
+    let data = general_purpose::STANDARD.decode(&body).map_err(|e| Error::Decode(e.to_string()))?;
+    Ok((headers, data))
+}
+
+/// Wrap DER bytes in an armored block with parameter headers
+fn armor(kind: Kind, params: &Parameters, der: &[u8]) -> String {
+    let headers = [("n", params.n.to_string()), ("q", params.q.to_string()), ("k", params.k.to_string())];
+    armor_block(kind.label(), &headers, der)
+}
+
+/// Extract the DER bytes of an armored block and check its headers against the decoded parameters
+fn unarmor(kind: Kind, pem: &str, count: fn(usize) -> usize) -> Result<(Parameters, PolyVec), Error> {
+    let (headers, der) = unarmor_block(kind.label(), pem)?;
+    let (params, polys) = decode_der(kind, &der, count)?;
+    for (name, value) in headers {
+        let expected = match name.as_str() {
+            "n" => params.n.to_string(),
+            "q" => params.q.to_string(),
+            "k" => params.k.to_string(),
+            _ => continue,
+        };
+        if value != expected {
+            return Err(Error::Decode(format!("header {} = {} does not match the encoded value {}", name, value, expected)));
+        }
+    }
+    Ok((params, polys))
+}
+
+fn public_key_count(k: usize) -> usize {
+    k * k + k
+}
+
+fn secret_key_count(k: usize) -> usize {
+    k
+}
+
+fn ciphertext_count(k: usize) -> usize {
+    k + 1
+}
+
+fn public_key_from_parts(params: Parameters, mut polys: PolyVec) -> PublicKey {
+    let k = params.k;
+    let t = polys.split_off(k * k);
+    let a = polys.chunks(k).map(|row| row.to_vec()).collect();
+    PublicKey { params, a, t }
+}
+
+fn ciphertext_from_parts(params: Parameters, mut polys: PolyVec) -> Ciphertext {
+    let v = polys.pop().expect("ciphertext has k + 1 polynomials");
+    Ciphertext { params, u: polys, v }
+}
+
+impl PublicKey {
+    /// Encode the public key as DER-like binary
+    pub fn to_der(&self) -> Vec<u8> {
+        let polys: Vec<&Polynomial<i64>> = self.a.iter().flatten().chain(self.t.iter()).collect();
+        encode_der(Kind::PublicKey, &self.params, &polys)
+    }
+
+    /// Decode a public key from DER-like binary
+    pub fn from_der(der: &[u8]) -> Result<Self, Error> {
+        let (params, polys) = decode_der(Kind::PublicKey, der, public_key_count)?;
+        Ok(public_key_from_parts(params, polys))
+    }
+
+    /// Encode the public key as an armored `MODULE-LWE PUBLIC KEY` block
+    /// # Example
+    /// ```
+    /// let params = module_lwe::utils::Parameters::builder().n(256).q(7681).k(2).build().unwrap();
+    /// let (pk, _) = module_lwe::keygen::keygen(&params, None);
+    /// let pk = module_lwe::types::PublicKey::new(&params, pk);
+    /// let pem = pk.to_pem();
+    /// assert!(pem.starts_with("-----BEGIN MODULE-LWE PUBLIC KEY-----\nn: 256\nq: 7681\nk: 2\n"));
+    /// assert_eq!(module_lwe::types::PublicKey::from_pem(&pem).unwrap(), pk);
+    /// ```
+    pub fn to_pem(&self) -> String {
+        armor(Kind::PublicKey, &self.params, &self.to_der())
+    }
+
+    /// Decode an armored `MODULE-LWE PUBLIC KEY` block
+    pub fn from_pem(pem: &str) -> Result<Self, Error> {
+        let (params, polys) = unarmor(Kind::PublicKey, pem, public_key_count)?;
+        Ok(public_key_from_parts(params, polys))
+    }
+}
+
+impl SecretKey {
+    /// Encode the secret key as DER-like binary
+    pub fn to_der(&self) -> Vec<u8> {
+        let polys: Vec<&Polynomial<i64>> = self.s.iter().collect();
+        encode_der(Kind::SecretKey, &self.params, &polys)
+    }
+
+    /// Decode a secret key from DER-like binary
+    pub fn from_der(der: &[u8]) -> Result<Self, Error> {
+        let (params, s) = decode_der(Kind::SecretKey, der, secret_key_count)?;
+        Ok(SecretKey { params, s })
+    }
+
+    /// Encode the secret key as an armored `MODULE-LWE SECRET KEY` block
+    pub fn to_pem(&self) -> String {
+        armor(Kind::SecretKey, &self.params, &self.to_der())
+    }
+
+    /// Decode an armored `MODULE-LWE SECRET KEY` block
+    pub fn from_pem(pem: &str) -> Result<Self, Error> {
+        let (params, s) = unarmor(Kind::SecretKey, pem, secret_key_count)?;
+        Ok(SecretKey { params, s })
+    }
+}
+
+impl Ciphertext {
+    /// Encode the ciphertext as DER-like binary
+    pub fn to_der(&self) -> Vec<u8> {
+        let polys: Vec<&Polynomial<i64>> = self.u.iter().chain(std::iter::once(&self.v)).collect();
+        encode_der(Kind::Ciphertext, &self.params, &polys)
+    }
+
+    /// Decode a ciphertext from DER-like binary
+    pub fn from_der(der: &[u8]) -> Result<Self, Error> {
+        let (params, polys) = decode_der(Kind::Ciphertext, der, ciphertext_count)?;
+        Ok(ciphertext_from_parts(params, polys))
+    }
+
+    /// Encode the ciphertext as an armored `MODULE-LWE CIPHERTEXT` block
+    pub fn to_pem(&self) -> String {
+        armor(Kind::Ciphertext, &self.params, &self.to_der())
+    }
+
+    /// Decode an armored `MODULE-LWE CIPHERTEXT` block
+    pub fn from_pem(pem: &str) -> Result<Self, Error> {
+        let (params, polys) = unarmor(Kind::Ciphertext, pem, ciphertext_count)?;
+        Ok(ciphertext_from_parts(params, polys))
+    }
+}
@@ -0,0 +1,107 @@
+use polynomial_ring::Polynomial;
+use ring_lwe::utils::{polyadd, polysub, polyinv};
+use crate::utils::{Parameters, PolyMatrix, PolyVec, add_vec, mul_vec_simple, mul_mat_vec_simple, transpose, scalar_mul, gen_small_vector, gadget_levels, SeedStream};
+use crate::homomorphic::{KeySwitchingKey, key_switch};
+
+/// Encrypt an arbitrary polynomial under a public key, so that the phase `v - sk*u` is `p + e`
+fn encrypt_poly(
+    pk: &(PolyMatrix, PolyVec),
+    p: &Polynomial<i64>,
+    params: &Parameters,
+    seeds: [Option<u64>; 3]
+) -> (PolyVec, Polynomial<i64>) {
+    let (n, q, k, f, omega) = (params.n, params.q, params.k, &params.f, params.omega);
+    let r = gen_small_vector(n, k, seeds[0]);
+    let e1 = gen_small_vector(n, k, seeds[1]);
+    let e2 = gen_small_vector(n, 1, seeds[2])[0].clone();
+    let u = add_vec(&mul_mat_vec_simple(&transpose(&pk.0), &r, q, f, omega), &e1, q, f);
+    let v = polyadd(&polyadd(&mul_vec_simple(&pk.1, &r, q, f, omega), &e2, q, f), p, q, f);
+    (u, v)
+}
+
+/// Generate a re-encryption key from the delegator's secret key to the delegatee's public key
+/// Row (j, l) encrypts `sk_a[j] * base^l` under `pk_b`, so the key has the layout of `homomorphic::KeySwitchingKey`
+/// and the delegatee never has to reveal a secret
+/// # Arguments
+/// * `sk_a` - delegator's secret key
+/// * `pk_b` - delegatee's public key
+/// * `base` - gadget decomposition base
+/// * `params` - Parameters for the module-LWE cryptosystem
+/// * `seed` - random seed
+/// # Returns
+/// * `rk` - re-encryption key for `reencrypt`
+pub fn reencryption_keygen(
+    sk_a: &[Polynomial<i64>],
+    pk_b: &(PolyMatrix, PolyVec),
+    base: i64,
+    params: &Parameters,
+    seed: Option<u64>
+) -> KeySwitchingKey {
+    let q = params.q;
+    let levels = gadget_levels(q, base);
+    let mut seeds = SeedStream::new(seed);
+    let keys = sk_a.iter()
+        .map(|s| {
+            let mut power = 1i64;
+            (0..levels)
+                .map(|_| {
+                    let row = encrypt_poly(pk_b, &scalar_mul(s, power, q), params, [seeds.next_seed(), seeds.next_seed(), seeds.next_seed()]);
+                    power = ((power as i128 * base as i128) % q as i128) as i64;
+                    row
+                })
+                .collect()
+        })
+        .collect();
+    KeySwitchingKey { base, levels, keys }
+}
+
+/// Transform a ciphertext under the delegator's key into one the delegatee can `decrypt`
+/// Key switching `u` gives (u', v') with phase close to `sk_a*u`, so (-u', v - v') has phase close to `v - sk_a*u`
+/// # Arguments
+/// * `ct` - ciphertext (u, v) from `encrypt::encrypt` under the delegator's public key
+/// * `rk` - re-encryption key from `reencryption_keygen`
+/// * `params` - Parameters for the module-LWE cryptosystem
+/// # Returns
+/// * `(u, v)` - ciphertext of the same message under the delegatee's key
+/// # Example
+/// ```
+/// use module_lwe::proxy::{reencryption_keygen, reencrypt};
+/// let params = module_lwe::utils::Parameters::builder().n(16).q(1073707009).k(2).build().unwrap();
+/// let (pk_a, sk_a) = module_lwe::keygen::keygen(&params, None);
+/// let (pk_b, sk_b) = module_lwe::keygen::keygen(&params, None);
+/// let rk = reencryption_keygen(&sk_a, &pk_b, 1 << 10, &params, None);
+/// let (u, v) = module_lwe::encrypt::encrypt(&pk_a.0, &pk_a.1, &[1, 0, 1, 1], &params, None);
+/// let (u, v) = reencrypt(&(u, v), &rk, &params);
+/// assert_eq!(module_lwe::decrypt::decrypt(&sk_b, &u, &v, &params)[..4], [1, 0, 1, 1]);
+/// ```
+pub fn reencrypt(ct: &(PolyVec, Polynomial<i64>), rk: &KeySwitchingKey, params: &Parameters) -> (PolyVec, Polynomial<i64>) {
+    let (q, f) = (params.q, &params.f);
+    let (u, v) = key_switch(rk, &ct.0, params);
+    (u.iter().map(|p| polyinv(p, q)).collect(), polysub(&ct.1, &v, q, f))
+}
+
+/// Worst-case noise that one re-encryption adds to a ciphertext
+/// Every row of the key carries at most the noise 2kn + 1 of a fresh encryption, and the proxy multiplies
+/// k * levels of them by gadget digits of magnitude at most base/2 + 1. A hop is safe while the old noise
+/// plus this bound stays below q/4, which rules out small moduli such as the default q = 12289.
+/// # Arguments
+/// * `base` - gadget decomposition base of the re-encryption key
+/// * `params` - Parameters for the module-LWE cryptosystem
+/// # Returns
+/// * `bound` - bound on the added noise per coefficient
+/// # Example
+/// ```
+/// use module_lwe::utils::Parameters;
+/// let params = Parameters::builder().n(16).q(1073707009).k(2).build().unwrap();
+/// assert!(module_lwe::proxy::reencryption_noise_bound(1 << 10, &params) < params.q() / 4);
+/// assert!(module_lwe::proxy::reencryption_noise_bound(2, &Parameters::default()) > Parameters::default().q() / 4);
+/// ```
+pub fn reencryption_noise_bound(base: i64, params: &Parameters) -> i64 {
+    let (n, q, k) = (params.n as i64, params.q, params.k as i64);
+    let levels = gadget_levels(q, base) as i64;
+    let fresh = 2 * k * n + 1;
+    k.saturating_mul(levels)
+        .saturating_mul(n)
+        .saturating_mul(base / 2 + 1)
+        .saturating_mul(fresh)
+}
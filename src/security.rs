@@ -0,0 +1,140 @@
+use std::f64::consts::{E, PI};
+use crate::utils::Parameters;
+use crate::noise::NoiseDistribution;
+
+/// Core-SVP cost exponent of sieving in dimension beta, classical
+const CLASSICAL_SIEVE: f64 = 0.292;
+/// Core-SVP cost exponent of sieving in dimension beta, quantum
+const QUANTUM_SIEVE: f64 = 0.265;
+/// Number of short vectors produced by one sieve, as a power of 2 per dimension
+const SIEVE_VECTORS: f64 = 0.2075;
+/// Smallest block size the root-Hermite-factor model is meaningful for
+const MIN_BLOCK_SIZE: usize = 40;
+
+/// Cost of the best parametrization found for one attack
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AttackEstimate {
+    /// BKZ block size
+    pub beta: usize,
+    /// Number of LWE samples used
+    pub samples: usize,
+    /// Estimated classical cost in bits
+    pub classical_bits: f64,
+    /// Estimated quantum cost in bits
+    pub quantum_bits: f64,
+}
+
+/// Core-SVP hardness of the primal and dual attacks
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SecurityEstimate {
+    /// Primal attack (unique-SVP on the embedding lattice)
+    pub primal: AttackEstimate,
+    /// Dual attack (distinguishing with short dual vectors)
+    pub dual: AttackEstimate,
+}
+
+impl SecurityEstimate {
+    /// Estimated classical bit security, the cheaper of the two attacks
+    pub fn classical_bits(&self) -> f64 {
+        self.primal.classical_bits.min(self.dual.classical_bits)
+    }
+
+    /// Estimated quantum bit security, the cheaper of the two attacks
+    pub fn quantum_bits(&self) -> f64 {
+        self.primal.quantum_bits.min(self.dual.quantum_bits)
+    }
+}
+
+/// Root Hermite factor achieved by BKZ with block size beta
+fn delta(beta: usize) -> f64 {
+    let b = beta as f64;
+    ((PI * b).powf(1.0 / b) * b / (2.0 * PI * E)).powf(1.0 / (2.0 * (b - 1.0)))
+}
+
+/// Primal attack: smallest block size that recovers the unique shortest vector of the Kannan embedding
+fn primal(d: usize, q: f64, sigma: f64) -> AttackEstimate {
+    let mut best: Option<(usize, usize)> = None;
+    let step = (d / 64).max(1);
+    for m in (step..=d).step_by(step) {
+        let dim = d + m + 1;
+        let upper = best.map_or(dim, |(beta, _)| beta.min(dim));
+        for beta in MIN_BLOCK_SIZE..upper {
+            let lhs = sigma * (beta as f64).sqrt();
+            let rhs = delta(beta).powf((2 * beta) as f64 - dim as f64 - 1.0) * q.powf(m as f64 / dim as f64);
+            if lhs <= rhs {
+                best = Some((beta, m));
+                break;
+            }
+        }
+    }
+    // lattices too small for the BKZ model are solved by a single SVP call
+    let (beta, samples) = best.unwrap_or((2 * d + 1, d));
+    AttackEstimate {
+        beta,
+        samples,
+        classical_bits: CLASSICAL_SIEVE * beta as f64,
+        quantum_bits: QUANTUM_SIEVE * beta as f64,
+    }
+}
+
+/// Dual attack: short vectors of the dual lattice distinguish LWE samples from uniform
+fn dual(d: usize, q: f64, sigma: f64) -> AttackEstimate {
+    let mut best: Option<AttackEstimate> = None;
+    let step = (d / 64).max(1);
+    for m in (step..=d).step_by(step) {
+        let dim = d + m;
+        for beta in MIN_BLOCK_SIZE..=dim {
+            // length of the shortest vector found by BKZ-beta and the resulting distinguishing advantage
+            let length = delta(beta).powf(dim as f64 - 1.0) * q.powf(d as f64 / dim as f64);
+            let tau = length * sigma / q;
+            let log2_eps = -2.0 * PI * PI * tau * tau / std::f64::consts::LN_2;
+            let repeat = (-2.0 * log2_eps - SIEVE_VECTORS * beta as f64).max(0.0);
+            let estimate = AttackEstimate {
+                beta,
+                samples: m,
+                classical_bits: CLASSICAL_SIEVE * beta as f64 + repeat,
+                quantum_bits: QUANTUM_SIEVE * beta as f64 + repeat,
+            };
+            if best.is_none_or(|b| estimate.classical_bits < b.classical_bits) {
+                best = Some(estimate);
+            }
+            // past this point larger blocks only cost more
+            if repeat == 0.0 {
+                break;
+            }
+        }
+    }
+    // lattices too small for the BKZ model are solved by a single SVP call
+    best.unwrap_or(AttackEstimate {
+        beta: 2 * d,
+        samples: d,
+        classical_bits: CLASSICAL_SIEVE * (2 * d) as f64,
+        quantum_bits: QUANTUM_SIEVE * (2 * d) as f64,
+    })
+}
+
+/// Estimate the core-SVP hardness of the module-LWE instance behind a public key
+/// The module-LWE instance is treated as plain LWE in dimension k*n with k*n samples
+/// # Arguments
+/// * `params` - Parameters for the module-LWE cryptosystem
+/// * `secret` - distribution of `sk`
+/// * `error` - distribution of `e`
+/// # Returns
+/// * `estimate` - primal and dual attack costs
+/// # Example
+/// ```
+/// use module_lwe::noise::NoiseDistribution;
+/// let params = module_lwe::utils::Parameters::default();
+/// let estimate = module_lwe::security::estimate(&params, &NoiseDistribution::Ternary, &NoiseDistribution::Ternary);
+/// assert!(estimate.classical_bits() > 128.0);
+/// ```
+pub fn estimate(params: &Parameters, secret: &NoiseDistribution, error: &NoiseDistribution) -> SecurityEstimate {
+    let d = params.n * params.k;
+    let q = params.q as f64;
+    // the embedding mixes secret and error coordinates, so use their average standard deviation
+    let sigma = ((secret.variance() + error.variance()) / 2.0).sqrt();
+    SecurityEstimate {
+        primal: primal(d, q, sigma),
+        dual: dual(d, q, sigma),
+    }
+}
@@ -0,0 +1,439 @@
+use polynomial_ring::Polynomial;
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use ring_lwe::utils::{polysub, gen_uniform_poly, mod_coeffs};
+use sha3::Shake256;
+use sha3::digest::{Update, ExtendableOutput, XofReader};
+use crate::utils::{Parameters, PolyMatrix, PolyVec, add_vec, mul_poly, mul_mat_vec_simple, gen_bounded_vector, SeedStream};
+use crate::error::Error;
+
+/// Modulus of the signature presets, 2^23 - 2^13 + 1 as in Dilithium
+pub const SIGN_Q: i64 = 8380417;
+
+/// Named signature parameter sets as (name, n, k, eta, tau, gamma1, gamma2), all with q = `SIGN_Q`
+pub const SIGN_PRESETS: [(&str, usize, usize, i64, usize, i64, i64); 3] = [
+    ("toy", 16, 2, 2, 8, 1 << 17, (SIGN_Q - 1) / 88),
+    ("level2", 256, 4, 2, 39, 1 << 17, (SIGN_Q - 1) / 88),
+    ("level3", 256, 5, 4, 49, 1 << 19, (SIGN_Q - 1) / 32),
+];
+
+/// Length in bytes of the challenge seed `c_tilde`
+pub const CHALLENGE_BYTES: usize = 32;
+
+/// Number of masks `sign` draws before giving up, far more than the presets need
+pub const MAX_SIGN_ATTEMPTS: usize = 1000;
+
+/// Parameters for the Fiat-Shamir-with-aborts signature scheme
+/// The public matrix is k x k, so one `Parameters` describes the ring and both module ranks
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignParameters {
+    /// Ring and module rank
+    pub(crate) params: Parameters,
+    /// Bound on the secret coefficients
+    pub(crate) eta: i64,
+    /// Number of nonzero coefficients of the challenge
+    pub(crate) tau: usize,
+    /// Bound on the masking coefficients
+    pub(crate) gamma1: i64,
+    /// Half of the high/low bits decomposition base
+    pub(crate) gamma2: i64,
+}
+
+impl SignParameters {
+    /// Validate signature parameters
+    /// # Arguments
+    /// * `params` - ring and module rank
+    /// * `eta` - secret coefficients are drawn from [-eta, eta]
+    /// * `tau` - number of +-1 coefficients of the challenge polynomial
+    /// * `gamma1` - masking coefficients are drawn from (-gamma1, gamma1)
+    /// * `gamma2` - low bits lie in (-gamma2, gamma2]
+    /// # Returns
+    /// * `sign_params` - parameters with 2*gamma2 dividing q - 1, n <= 256, tau <= min(n, 64) and beta = tau*eta below gamma1 and gamma2
+    /// # Example
+    /// ```
+    /// use module_lwe::sign::{SignParameters, SIGN_Q};
+    /// let params = module_lwe::utils::Parameters::builder().n(16).q(SIGN_Q).k(2).build().unwrap();
+    /// assert!(SignParameters::new(params.clone(), 2, 8, 1 << 17, (SIGN_Q - 1) / 88).is_ok());
+    /// assert!(SignParameters::new(params.clone(), 2, 8, 1 << 17, 1000).is_err());
+    /// assert!(SignParameters::new(params, 2, 17, 1 << 17, (SIGN_Q - 1) / 88).is_err());
+    /// ```
+    pub fn new(params: Parameters, eta: i64, tau: usize, gamma1: i64, gamma2: i64) -> Result<Self, Error> {
+        let (n, q) = (params.n, params.q);
+        // the challenge sampler reads one byte per position
+        if n > 256 {
+            return Err(Error::InvalidParameters(format!("n = {} is above 256", n)));
+        }
+        if eta < 1 || tau < 1 || tau > n {
+            return Err(Error::InvalidParameters(format!("need eta >= 1 and 1 <= tau <= n, got eta = {}, tau = {}", eta, tau)));
+        }
+        // and takes the signs of the tau nonzero coefficients from one 64-bit word
+        if tau > 64 {
+            return Err(Error::InvalidParameters(format!("tau = {} is above 64", tau)));
+        }
+        if gamma2 < 1 || (q - 1) % (2 * gamma2) != 0 {
+            return Err(Error::InvalidParameters(format!("2*gamma2 = {} does not divide q - 1 = {}", 2 * gamma2, q - 1)));
+        }
+        let beta = tau as i64 * eta;
+        if gamma1 <= beta || gamma2 <= beta || 2 * gamma1 >= q {
+            return Err(Error::InvalidParameters(format!("need beta = {} < gamma1, gamma2 and gamma1 < q/2", beta)));
+        }
+        Ok(SignParameters { params, eta, tau, gamma1, gamma2 })
+    }
+
+    /// Parameters of a named preset from `SIGN_PRESETS`
+    /// # Example
+    /// ```
+    /// use module_lwe::sign::SignParameters;
+    /// assert_eq!(SignParameters::preset("level2").unwrap().params().k(), 4);
+    /// assert!(SignParameters::preset("level9").is_none());
+    /// ```
+    pub fn preset(name: &str) -> Option<SignParameters> {
+        SIGN_PRESETS.iter()
+            .find(|(preset, ..)| *preset == name)
+            .map(|&(_, n, k, eta, tau, gamma1, gamma2)| {
+                let params = Parameters::builder().n(n).q(SIGN_Q).k(k).build().expect("presets are valid");
+                SignParameters::new(params, eta, tau, gamma1, gamma2).expect("presets are valid")
+            })
+    }
+
+    /// Ring and module rank
+    pub fn params(&self) -> &Parameters {
+        &self.params
+    }
+
+    /// Bound on the secret coefficients
+    pub fn eta(&self) -> i64 {
+        self.eta
+    }
+
+    /// Number of nonzero coefficients of the challenge
+    pub fn tau(&self) -> usize {
+        self.tau
+    }
+
+    /// Bound on the masking coefficients
+    pub fn gamma1(&self) -> i64 {
+        self.gamma1
+    }
+
+    /// Half of the high/low bits decomposition base
+    pub fn gamma2(&self) -> i64 {
+        self.gamma2
+    }
+
+    /// Largest coefficient of c*s1 or c*s2, tau*eta
+    pub fn beta(&self) -> i64 {
+        self.tau as i64 * self.eta
+    }
+}
+
+/// Public key for signature verification
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifyingKey {
+    /// Uniform public matrix
+    pub a: PolyMatrix,
+    /// t = a*s1 + s2
+    pub t: PolyVec,
+}
+
+/// Secret key for signing
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SigningKey {
+    /// The matching public key
+    pub vk: VerifyingKey,
+    /// Secret vector multiplied by the challenge in the response
+    pub s1: PolyVec,
+    /// Secret error vector
+    pub s2: PolyVec,
+}
+
+/// Signature (c_tilde, z)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Signature {
+    /// Seed of the challenge polynomial
+    pub c_tilde: [u8; CHALLENGE_BYTES],
+    /// Response y + c*s1
+    pub z: PolyVec,
+}
+
+impl Signature {
+    /// Encode as `c_tilde` followed by the coefficients of `z` as 32-bit little-endian integers
+    pub fn to_bytes(&self, sign_params: &SignParameters) -> Vec<u8> {
+        let n = sign_params.params.n;
+        let mut out = self.c_tilde.to_vec();
+        for p in self.z.iter() {
+            for c in padded(p, n) {
+                out.extend_from_slice(&(c as i32).to_le_bytes());
+            }
+        }
+        out
+    }
+
+    /// Decode a signature produced by `to_bytes`
+    pub fn from_bytes(bytes: &[u8], sign_params: &SignParameters) -> Result<Self, Error> {
+        let (n, k) = (sign_params.params.n, sign_params.params.k);
+        let expected = CHALLENGE_BYTES + 4 * n * k;
+        if bytes.len() != expected {
+            return Err(Error::Decode(format!("signature has {} bytes, expected {}", bytes.len(), expected)));
+        }
+        let (c_tilde, z) = bytes.split_at(CHALLENGE_BYTES);
+        let coeffs: Vec<i64> = z.chunks_exact(4)
+            .map(|b| i32::from_le_bytes([b[0], b[1], b[2], b[3]]) as i64)
+            .collect();
+        Ok(Signature {
+            c_tilde: c_tilde.try_into().expect("length checked above"),
+            z: coeffs.chunks(n).map(|c| Polynomial::new(c.to_vec())).collect(),
+        })
+    }
+}
+
+/// Split r into high and low bits r = r1*alpha + r0 with r0 in (-alpha/2, alpha/2]
+/// The top residue q - 1 maps to r1 = 0, r0 = -1 so that r1 ranges over [0, (q-1)/alpha)
+/// # Arguments
+/// * `r` - integer, reduced mod q first
+/// * `alpha` - decomposition base 2*gamma2, an even divisor of q - 1
+/// * `q` - modulus
+/// # Returns
+/// * `(r1, r0)` - high and low bits
+/// # Example
+/// ```
+/// use module_lwe::sign::{decompose, SIGN_Q};
+/// let alpha = (SIGN_Q - 1) / 44;
+/// assert_eq!(decompose(3 * alpha + 5, alpha, SIGN_Q), (3, 5));
+/// assert_eq!(decompose(-1, alpha, SIGN_Q), (0, -1));
+/// ```
+pub fn decompose(r: i64, alpha: i64, q: i64) -> (i64, i64) {
+    let r = r.rem_euclid(q);
+    let mut r0 = r % alpha;
+    if r0 > alpha / 2 {
+        r0 -= alpha;
+    }
+    if r - r0 == q - 1 {
+        (0, r0 - 1)
+    } else {
+        ((r - r0) / alpha, r0)
+    }
+}
+
+/// Coefficients of a polynomial padded to length n
+fn padded(p: &Polynomial<i64>, n: usize) -> Vec<i64> {
+    let mut coeffs = p.coeffs().to_vec();
+    coeffs.resize(n, 0);
+    coeffs
+}
+
+/// Largest absolute value of a centered coefficient
+fn inf_norm(v: &[Polynomial<i64>], q: i64) -> i64 {
+    v.iter()
+        .flat_map(|p| p.coeffs().iter())
+        .map(|&c| {
+            let c = c.rem_euclid(q);
+            if c > q / 2 { q - c } else { c }
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+/// High bits of every coefficient, as polynomials of length n
+fn high_bits(v: &[Polynomial<i64>], sign_params: &SignParameters) -> Vec<Vec<i64>> {
+    let (n, q) = (sign_params.params.n, sign_params.params.q);
+    v.iter()
+        .map(|p| padded(p, n).into_iter().map(|c| decompose(c, 2 * sign_params.gamma2, q).0).collect())
+        .collect()
+}
+
+/// Low bits of every coefficient
+fn low_bits(v: &[Polynomial<i64>], sign_params: &SignParameters) -> PolyVec {
+    let q = sign_params.params.q;
+    v.iter()
+        .map(|p| Polynomial::new(p.coeffs().iter().map(|&c| decompose(c, 2 * sign_params.gamma2, q).1).collect()))
+        .collect()
+}
+
+/// Absorb polynomials into a hash as 32-bit little-endian coefficients in [0, q)
+fn absorb(hasher: &mut Shake256, polys: &[Polynomial<i64>], n: usize, q: i64) {
+    for p in polys {
+        for c in padded(p, n) {
+            hasher.update(&(c.rem_euclid(q) as u32).to_le_bytes());
+        }
+    }
+}
+
+/// Hash of the public key, bound into every signed message
+fn key_hash(vk: &VerifyingKey, sign_params: &SignParameters) -> [u8; 32] {
+    let (n, q) = (sign_params.params.n, sign_params.params.q);
+    let mut hasher = Shake256::default();
+    for row in vk.a.iter() {
+        absorb(&mut hasher, row, n, q);
+    }
+    absorb(&mut hasher, &vk.t, n, q);
+    let mut tr = [0u8; 32];
+    hasher.finalize_xof().read(&mut tr);
+    tr
+}
+
+/// Message representative mu = H(H(vk) || msg)
+fn message_hash(vk: &VerifyingKey, msg: &[u8], sign_params: &SignParameters) -> [u8; 64] {
+    let mut hasher = Shake256::default();
+    hasher.update(&key_hash(vk, sign_params));
+    hasher.update(msg);
+    let mut mu = [0u8; 64];
+    hasher.finalize_xof().read(&mut mu);
+    mu
+}
+
+/// Challenge seed c_tilde = H(mu || w1)
+fn challenge_hash(mu: &[u8], w1: &[Vec<i64>]) -> [u8; CHALLENGE_BYTES] {
+    let mut hasher = Shake256::default();
+    hasher.update(mu);
+    for c in w1.iter().flatten() {
+        hasher.update(&(*c as u32).to_le_bytes());
+    }
+    let mut c_tilde = [0u8; CHALLENGE_BYTES];
+    hasher.finalize_xof().read(&mut c_tilde);
+    c_tilde
+}
+
+/// Expand a challenge seed into a polynomial with exactly tau coefficients in {-1, 1}
+/// Follows Dilithium's SampleInBall: an inside-out Fisher-Yates shuffle driven by SHAKE256(seed)
+/// # Arguments
+/// * `c_tilde` - challenge seed
+/// * `n` - polynomial degree, at most 256
+/// * `tau` - number of nonzero coefficients, at most 64
+/// # Returns
+/// * `c` - challenge polynomial
+/// # Example
+/// ```
+/// let c = module_lwe::sign::sample_in_ball(&[7; 32], 256, 39);
+/// assert_eq!(c.coeffs().iter().filter(|&&x| x != 0).count(), 39);
+/// assert!(c.coeffs().iter().all(|x| x.abs() <= 1));
+/// ```
+pub fn sample_in_ball(c_tilde: &[u8], n: usize, tau: usize) -> Polynomial<i64> {
+    let mut hasher = Shake256::default();
+    hasher.update(c_tilde);
+    let mut reader = hasher.finalize_xof();
+    let mut signs = [0u8; 8];
+    reader.read(&mut signs);
+    let mut signs = u64::from_le_bytes(signs);
+    let mut c = vec![0i64; n];
+    let mut byte = [0u8; 1];
+    for i in n - tau..n {
+        let j = loop {
+            reader.read(&mut byte);
+            if (byte[0] as usize) <= i {
+                break byte[0] as usize;
+            }
+        };
+        c[i] = c[j];
+        c[j] = 1 - 2 * (signs & 1) as i64;
+        signs >>= 1;
+    }
+    Polynomial::new(c)
+}
+
+/// Generate a signature key pair
+/// # Arguments
+/// * `sign_params` - signature parameters
+/// * `seed` - random seed
+/// # Returns
+/// * `(vk, sk)` - verifying key (a, t = a*s1 + s2) and signing key (s1, s2)
+/// # Example
+/// ```
+/// let sign_params = module_lwe::sign::SignParameters::preset("toy").unwrap();
+/// let (vk, sk) = module_lwe::sign::keygen(&sign_params, None);
+/// assert_eq!(vk, sk.vk);
+/// ```
+pub fn keygen(sign_params: &SignParameters, seed: Option<u64>) -> (VerifyingKey, SigningKey) {
+    let SignParameters { params, eta, .. } = sign_params;
+    let (n, q, k, f, omega) = (params.n, params.q, params.k, &params.f, params.omega);
+    let mut seeds = SeedStream::new(seed);
+    let a: PolyMatrix = (0..k)
+        .map(|_| (0..k).map(|_| mod_coeffs(gen_uniform_poly(n, q, seeds.next_seed()), q)).collect())
+        .collect();
+    let s1 = gen_bounded_vector(n, k, *eta, seeds.next_seed());
+    let s2 = gen_bounded_vector(n, k, *eta, seeds.next_seed());
+    let t = add_vec(&mul_mat_vec_simple(&a, &s1, q, f, omega), &s2, q, f);
+    let vk = VerifyingKey { a, t };
+    (vk.clone(), SigningKey { vk, s1, s2 })
+}
+
+/// Sign a message
+/// Masks are drawn until the response z = y + c*s1 is short enough to hide s1 and the low bits of
+/// w - c*s2 leave the high bits of w unchanged, so the verifier recomputes the same challenge
+/// # Arguments
+/// * `sk` - signing key
+/// * `msg` - message bytes
+/// * `sign_params` - signature parameters
+/// * `seed` - random seed for the masks
+/// # Returns
+/// * `signature` - signature (c_tilde, z), or an error if all `MAX_SIGN_ATTEMPTS` masks are rejected
+/// # Example
+/// ```
+/// use module_lwe::sign::{SignParameters, keygen, sign, verify};
+/// let sign_params = SignParameters::preset("toy").unwrap();
+/// let (vk, sk) = keygen(&sign_params, None);
+/// let signature = sign(&sk, b"hello", &sign_params, None).unwrap();
+/// assert!(verify(&vk, b"hello", &signature, &sign_params));
+/// assert!(!verify(&vk, b"hellp", &signature, &sign_params));
+/// ```
+pub fn sign(sk: &SigningKey, msg: &[u8], sign_params: &SignParameters, seed: Option<u64>) -> Result<Signature, Error> {
+    let SignParameters { params, tau, gamma1, gamma2, .. } = sign_params;
+    let (n, q, k, f, omega) = (params.n, params.q, params.k, &params.f, params.omega);
+    let beta = sign_params.beta();
+    let mu = message_hash(&sk.vk, msg, sign_params);
+    let mut rng = match seed {
+        Some(s) => StdRng::seed_from_u64(s),
+        None => StdRng::from_entropy(),
+    };
+    for _ in 0..MAX_SIGN_ATTEMPTS {
+        let y = gen_bounded_vector(n, k, gamma1 - 1, Some(rng.gen()));
+        let w = mul_mat_vec_simple(&sk.vk.a, &y, q, f, omega);
+        let w1 = high_bits(&w, sign_params);
+        let c_tilde = challenge_hash(&mu, &w1);
+        let c = sample_in_ball(&c_tilde, n, *tau);
+
+        let z = add_vec(&y, &sk.s1.iter().map(|s| mul_poly(&c, s, q, f, omega)).collect::<Vec<_>>(), q, f);
+        if inf_norm(&z, q) >= gamma1 - beta {
+            continue;
+        }
+        let r: PolyVec = w.iter()
+            .zip(sk.s2.iter())
+            .map(|(wi, s)| polysub(wi, &mul_poly(&c, s, q, f, omega), q, f))
+            .collect();
+        if inf_norm(&low_bits(&r, sign_params), q) >= gamma2 - beta || high_bits(&r, sign_params) != w1 {
+            continue;
+        }
+        return Ok(Signature { c_tilde, z });
+    }
+    Err(Error::InvalidParameters(format!("every one of {} masks was rejected", MAX_SIGN_ATTEMPTS)))
+}
+
+/// Verify a signature
+/// # Arguments
+/// * `vk` - verifying key
+/// * `msg` - message bytes
+/// * `signature` - signature (c_tilde, z)
+/// * `sign_params` - signature parameters
+/// # Returns
+/// * `valid` - whether z is short and the high bits of a*z - c*t hash back to c_tilde
+pub fn verify(vk: &VerifyingKey, msg: &[u8], signature: &Signature, sign_params: &SignParameters) -> bool {
+    let SignParameters { params, tau, gamma1, .. } = sign_params;
+    let (n, q, k, f, omega) = (params.n, params.q, params.k, &params.f, params.omega);
+    if signature.z.len() != k || signature.z.iter().any(|p| p.coeffs().len() > n) {
+        return false;
+    }
+    if vk.a.len() != k || vk.a.iter().any(|row| row.len() != k) || vk.t.len() != k {
+        return false;
+    }
+    if inf_norm(&signature.z, q) >= gamma1 - sign_params.beta() {
+        return false;
+    }
+    let c = sample_in_ball(&signature.c_tilde, n, *tau);
+    let w: PolyVec = mul_mat_vec_simple(&vk.a, &signature.z, q, f, omega)
+        .iter()
+        .zip(vk.t.iter())
+        .map(|(az, t)| polysub(az, &mul_poly(&c, t, q, f, omega), q, f))
+        .collect();
+    let mu = message_hash(vk, msg, sign_params);
+    challenge_hash(&mu, &high_bits(&w, sign_params)) == signature.c_tilde
+}
@@ -0,0 +1,90 @@
+use polynomial_ring::Polynomial;
+use ring_lwe::utils::{polyadd, polysub, gen_uniform_poly, mod_coeffs, nearest_int};
+use crate::utils::{Parameters, PolyVec, mul_vec_simple, gen_small_vector, derive_seed, SeedStream};
+
+/// Secret-key ciphertext whose vector u is expanded from a public seed
+/// Only the seed and v are stored, about 1/(k + 1) of the size of (u, v)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SeededCiphertext {
+    /// Public seed of the uniform vector u
+    pub seed: u64,
+    /// Ciphertext polynomial sk*u + e - m*half_q
+    pub v: Polynomial<i64>,
+}
+
+impl SeededCiphertext {
+    /// Expand the ciphertext into the (u, v) form of `encrypt::encrypt`, for `decrypt::decrypt` and the homomorphic operations
+    pub fn expand(&self, params: &Parameters) -> (PolyVec, Polynomial<i64>) {
+        (expand_u(self.seed, params), self.v.clone())
+    }
+}
+
+/// Uniform vector u derived from a public seed
+/// # Arguments
+/// * `seed` - public seed
+/// * `params` - Parameters for the module-LWE cryptosystem
+/// # Returns
+/// * `u` - k uniform polynomials with centered coefficients
+pub fn expand_u(seed: u64, params: &Parameters) -> PolyVec {
+    let (n, q, k) = (params.n, params.q, params.k);
+    // hash seed || i, so that the polynomials of nearby seeds are unrelated
+    (0..k)
+        .map(|i| mod_coeffs(gen_uniform_poly(n, q, Some(derive_seed(seed, i as u64))), q))
+        .collect()
+}
+
+/// Compute v = sk*u + e - m*half_q for a given u
+fn encrypt_with_u(sk: &[Polynomial<i64>], u: &[Polynomial<i64>], m_b: &[i64], params: &Parameters, seed: Option<u64>) -> Polynomial<i64> {
+    let (n, q, f, omega) = (params.n, params.q, &params.f, params.omega);
+    let e = gen_small_vector(n, 1, seed).remove(0);
+    let half_q = nearest_int(q, 2);
+    let m = Polynomial::new(vec![half_q]) * Polynomial::new(m_b.to_vec());
+    polysub(&polyadd(&mul_vec_simple(sk, u, q, f, omega), &e, q, f), &m, q, f)
+}
+
+/// Encrypt a message to the holder of the secret key, without a public key
+/// The phase v - sk*u is e - m*half_q, so the ciphertext decrypts with `decrypt::decrypt` and has the noise of e alone
+/// # Arguments
+/// * `sk` - secret key
+/// * `m_b` - binary message
+/// * `params` - Parameters for the module-LWE cryptosystem
+/// * `seed` - random seed
+/// # Returns
+/// * `(u, v)` - ciphertext with uniform u
+/// # Example
+/// ```
+/// let params = module_lwe::utils::Parameters::default();
+/// let (_, sk) = module_lwe::keygen::keygen(&params, None);
+/// let (u, v) = module_lwe::symmetric::encrypt_sk(&sk, &[1, 0, 1], &params, None);
+/// assert_eq!(module_lwe::decrypt::decrypt(&sk, &u, &v, &params)[..3], [1, 0, 1]);
+/// ```
+pub fn encrypt_sk(sk: &[Polynomial<i64>], m_b: &[i64], params: &Parameters, seed: Option<u64>) -> (PolyVec, Polynomial<i64>) {
+    let (n, q, k) = (params.n, params.q, params.k);
+    let mut seeds = SeedStream::new(seed);
+    let u: PolyVec = (0..k).map(|_| mod_coeffs(gen_uniform_poly(n, q, seeds.next_seed()), q)).collect();
+    let v = encrypt_with_u(sk, &u, m_b, params, seeds.next_seed());
+    (u, v)
+}
+
+/// Encrypt a message to the holder of the secret key, deriving u from a public seed
+/// The seed must not be reused across messages, since two ciphertexts with the same u reveal the difference of their noise and messages
+/// # Arguments
+/// * `sk` - secret key
+/// * `m_b` - binary message
+/// * `u_seed` - fresh public seed for u
+/// * `params` - Parameters for the module-LWE cryptosystem
+/// * `seed` - random seed for the noise
+/// # Returns
+/// * `ct` - compact ciphertext; `ct.expand(params)` gives (u, v)
+/// # Example
+/// ```
+/// let params = module_lwe::utils::Parameters::default();
+/// let (_, sk) = module_lwe::keygen::keygen(&params, None);
+/// let ct = module_lwe::symmetric::encrypt_sk_seeded(&sk, &[1, 1, 0], 48, &params, None);
+/// let (u, v) = ct.expand(&params);
+/// assert_eq!(module_lwe::decrypt::decrypt(&sk, &u, &v, &params)[..3], [1, 1, 0]);
+/// ```
+pub fn encrypt_sk_seeded(sk: &[Polynomial<i64>], m_b: &[i64], u_seed: u64, params: &Parameters, seed: Option<u64>) -> SeededCiphertext {
+    let u = expand_u(u_seed, params);
+    SeededCiphertext { seed: u_seed, v: encrypt_with_u(sk, &u, m_b, params, seed) }
+}
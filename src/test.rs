@@ -3,8 +3,35 @@ mod tests {
     use crate::keygen::{keygen,keygen_string};
     use crate::encrypt::{encrypt,encrypt_string};
     use crate::decrypt::{decrypt,decrypt_string};
-    use crate::utils::{Parameters,add_vec};
+    use crate::utils::{Parameters,add_vec,mul_vec_simple};
+    use crate::homomorphic::mul;
+    use crate::keygen::relin_keygen;
+    use crate::security::estimate;
+    use crate::noise::{NoiseDistribution, measure_noise, noise_budget, noise_variance, failure_probability, failure_probability_log2, max_rerandomizations};
     use ring_lwe::utils::polyadd;
+    use crate::error::Error;
+    use proptest::prelude::*;
+    use polynomial_ring::Polynomial;
+
+    // Small ring with a ~30-bit modulus, leaving headroom for multiplication noise
+    fn mul_params() -> Parameters {
+        Parameters::builder().n(16).q(1073707009).k(2).build().unwrap()
+    }
+
+    // Error e = t - a*sk of a key pair, with centered coefficients
+    fn key_error(pk: &(Vec<Vec<Polynomial<i64>>>, Vec<Polynomial<i64>>), sk: &[Polynomial<i64>], params: &Parameters) -> Vec<Polynomial<i64>> {
+        let a_sk = crate::utils::mul_mat_vec_simple(&pk.0, sk, params.q, &params.f, params.omega);
+        pk.1.iter().zip(a_sk.iter())
+            .map(|(t, a_sk)| ring_lwe::utils::mod_coeffs(ring_lwe::utils::polysub(t, a_sk, params.q, &params.f), params.q))
+            .collect()
+    }
+
+    // Decrypt all n coefficients of a ciphertext, since decrypt drops trailing zeros
+    fn decrypt_full(sk: &[Polynomial<i64>], ct: &(Vec<Polynomial<i64>>, Polynomial<i64>), params: &Parameters) -> Vec<i64> {
+        let mut m = decrypt(sk, &ct.0, &ct.1, params);
+        m.resize(params.n, 0);
+        m
+    }
 
     // Test for basic keygen/encrypt/decrypt of a message
     #[test]
@@ -15,8 +42,8 @@ mod tests {
         let keypair = keygen_string(&params,seed);
         let pk_string = keypair.get("public").unwrap();
         let sk_string = keypair.get("secret").unwrap();
-        let ciphertext_string = encrypt_string(&pk_string, &message, &params,seed);
-        let decrypted_message = decrypt_string(&sk_string, &ciphertext_string, &params);
+        let ciphertext_string = encrypt_string(pk_string, &message, &params,seed).unwrap();
+        let decrypted_message = decrypt_string(sk_string, &ciphertext_string, &params).unwrap();
         assert_eq!(message, decrypted_message, "test failed: {} != {}", message, decrypted_message);
     }
 
@@ -54,4 +81,1217 @@ mod tests {
 
         assert_eq!(decrypted_sum, plaintext_sum, "test failed: {:?} != {:?}", decrypted_sum, plaintext_sum);
     }
-}
\ No newline at end of file
+
+    // Test homomorphic multiplication of encrypted bits:
+    // constant polynomials multiply like an AND gate
+    #[test]
+    pub fn test_hom_mul_and() {
+        let seed = None;
+        let params = mul_params();
+        let n = params.n;
+        let (pk, sk) = keygen(&params, seed);
+        let rlk = relin_keygen(&sk, 1 << 10, &params, seed);
+
+        for b0 in 0..2 {
+            for b1 in 0..2 {
+                let ct0 = encrypt(&pk.0, &pk.1, &[b0], &params, seed);
+                let ct1 = encrypt(&pk.0, &pk.1, &[b1], &params, seed);
+                let decrypted = decrypt_full(&sk, &mul(&ct0, &ct1, &rlk, &params), &params);
+                let mut expected = vec![b0 & b1];
+                expected.resize(n, 0);
+                assert_eq!(decrypted, expected, "test failed: {} AND {}", b0, b1);
+            }
+        }
+    }
+
+    // Test a depth-two circuit: (b0 AND b1) AND b2
+    #[test]
+    pub fn test_hom_mul_depth_two() {
+        let seed = None;
+        let params = mul_params();
+        let n = params.n;
+        let (pk, sk) = keygen(&params, seed);
+        let rlk = relin_keygen(&sk, 1 << 10, &params, seed);
+
+        let cts: Vec<_> = [1, 1, 1].iter().map(|&b| encrypt(&pk.0, &pk.1, &[b], &params, seed)).collect();
+        let ct = mul(&mul(&cts[0], &cts[1], &rlk, &params), &cts[2], &rlk, &params);
+        let decrypted = decrypt_full(&sk, &ct, &params);
+        let mut expected = vec![1];
+        expected.resize(n, 0);
+        assert_eq!(decrypted, expected, "test failed: {:?} != {:?}", decrypted, expected);
+    }
+
+    // Test that measured noise is bounded for fresh ciphertexts and adds up under homomorphic addition
+    #[test]
+    pub fn test_noise_measurement() {
+        let seed = None;
+        let params = Parameters::default();
+        let (n, q, k, f) = (params.n, params.q, params.k, &params.f);
+        let (pk, sk) = keygen(&params, seed);
+        let zero = vec![0; n];
+
+        let c0 = encrypt(&pk.0, &pk.1, &zero, &params, seed);
+        let c1 = encrypt(&pk.0, &pk.1, &zero, &params, seed);
+        let e0 = measure_noise(&sk, &c0.0, &c0.1, &zero, &params);
+        let e1 = measure_noise(&sk, &c1.0, &c1.1, &zero, &params);
+        let bound = 2 * (k * n) as i64 + 1;
+        assert!(e0.iter().chain(e1.iter()).all(|e| e.abs() <= bound));
+
+        let sum = (add_vec(&c0.0, &c1.0, q, f), polyadd(&c0.1, &c1.1, q, f));
+        let e_sum = measure_noise(&sk, &sum.0, &sum.1, &zero, &params);
+        let expected: Vec<i64> = e0.iter().zip(e1.iter()).map(|(a, b)| a + b).collect();
+        assert_eq!(e_sum, expected);
+        assert!(noise_budget(&sk, &sum.0, &sum.1, &params) <= noise_budget(&sk, &c0.0, &c0.1, &params).max(noise_budget(&sk, &c1.0, &c1.1, &params)));
+    }
+
+    // Test the analytic failure probability estimate
+    #[test]
+    pub fn test_failure_probability() {
+        let params = Parameters::default();
+        let ternary = NoiseDistribution::Ternary;
+        assert!(failure_probability(&params, &ternary, &ternary) < 1e-100);
+
+        // more noise means a higher failure probability
+        let variance = noise_variance(&params, &ternary, &ternary);
+        assert!(failure_probability_log2(&params, variance) < failure_probability_log2(&params, 100.0 * variance));
+
+        // noise comparable to q/4 fails almost surely
+        let wide = NoiseDistribution::Gaussian(50.0);
+        assert!(failure_probability(&params, &wide, &wide) > 0.99);
+    }
+
+    // Test the core-SVP security estimate on default and toy parameters
+    #[test]
+    pub fn test_security_estimate() {
+        let ternary = NoiseDistribution::Ternary;
+        let default = estimate(&Parameters::default(), &ternary, &ternary);
+        assert!(default.classical_bits() >= 128.0);
+        assert!(default.quantum_bits() <= default.classical_bits());
+
+        // a toy ring is broken immediately, and a larger rank is harder
+        let toy = estimate(&mul_params(), &ternary, &ternary);
+        assert!(toy.classical_bits() < 32.0);
+        let rank_four = Parameters::builder().n(16).q(1073707009).k(4).build().unwrap();
+        assert!(estimate(&rank_four, &ternary, &ternary).classical_bits() >= toy.classical_bits());
+    }
+
+    // Test that the builder derives omega and f and rejects invalid combinations
+    #[test]
+    pub fn test_parameters_builder() {
+        let params = Parameters::builder().n(256).q(7681).k(3).build().unwrap();
+        assert_eq!((params.n(), params.q(), params.k()), (256, 7681, 3));
+        assert_eq!(params.omega(), ntt::omega(7681, 512));
+        assert_eq!(params.f().coeffs().len(), 257);
+
+        let invalid = [
+            Parameters::builder().n(384),            // n not a power of two
+            Parameters::builder().q(12288),          // q not prime
+            Parameters::builder().n(1024).q(7681),   // 7681 != 1 mod 2048
+            Parameters::builder().k(0),              // empty module
+            Parameters::builder().q(2147483713),     // q too large for the NTT
+        ];
+        for builder in invalid {
+            assert!(matches!(builder.build(), Err(Error::InvalidParameters(_))), "accepted {:?}", builder);
+        }
+    }
+
+    // Test serde round trips of parameters, keys and ciphertexts through JSON, bincode and CBOR
+    #[cfg(feature = "serde")]
+    #[test]
+    pub fn test_serde_round_trip() {
+        use crate::types::{PublicKey, SecretKey, Ciphertext};
+
+        let params = Parameters::builder().n(256).q(7681).k(2).build().unwrap();
+        let (pk, sk) = keygen(&params, None);
+        let ct = encrypt(&pk.0, &pk.1, &[1, 0, 1, 1], &params, None);
+        let pk = PublicKey::new(&params, pk);
+        let sk = SecretKey::new(&params, sk);
+        let ct = Ciphertext::new(&params, ct);
+
+        let json = serde_json::to_string(&pk).unwrap();
+        assert_eq!(serde_json::from_str::<PublicKey>(&json).unwrap(), pk);
+        let json = serde_json::to_string(&sk).unwrap();
+        assert_eq!(serde_json::from_str::<SecretKey>(&json).unwrap(), sk);
+        let json = serde_json::to_string(&ct).unwrap();
+        assert_eq!(serde_json::from_str::<Ciphertext>(&json).unwrap(), ct);
+        let json = serde_json::to_string(&params).unwrap();
+        assert_eq!(json, r#"{"n":256,"q":7681,"k":2}"#);
+        assert_eq!(serde_json::from_str::<Parameters>(&json).unwrap(), params);
+
+        let bytes = bincode::serialize(&pk).unwrap();
+        assert_eq!(bincode::deserialize::<PublicKey>(&bytes).unwrap(), pk);
+        let bytes = bincode::serialize(&ct).unwrap();
+        assert_eq!(bincode::deserialize::<Ciphertext>(&bytes).unwrap(), ct);
+
+        let mut cbor = vec![];
+        ciborium::into_writer(&sk, &mut cbor).unwrap();
+        assert_eq!(ciborium::from_reader::<SecretKey, _>(cbor.as_slice()).unwrap(), sk);
+    }
+
+    // Test that deserialization range-checks parameters and coefficients
+    #[cfg(feature = "serde")]
+    #[test]
+    pub fn test_serde_range_checks() {
+        use crate::types::SecretKey;
+
+        assert!(serde_json::from_str::<Parameters>(r#"{"n":256,"q":7680,"k":2}"#).is_err());
+        assert!(serde_json::from_str::<Parameters>(r#"{"n":-1,"q":7681,"k":2}"#).is_err());
+
+        let params = Parameters::builder().n(2).q(13).k(1).build().unwrap();
+        let sk = SecretKey::new(&params, vec![Polynomial::new(vec![1, -1])]);
+        let json = serde_json::to_string(&sk).unwrap();
+        assert_eq!(json, r#"{"params":{"n":2,"q":13,"k":1},"s":[[1,12]]}"#);
+        assert_eq!(serde_json::from_str::<SecretKey>(&json).unwrap(), sk);
+        for bad in [
+            r#"{"params":{"n":2,"q":13,"k":1},"s":[[1,13]]}"#,    // coefficient >= q
+            r#"{"params":{"n":2,"q":13,"k":1},"s":[[-1,0]]}"#,    // negative coefficient
+            r#"{"params":{"n":2,"q":13,"k":1},"s":[[1,0,0]]}"#,   // too many coefficients
+            r#"{"params":{"n":2,"q":13,"k":1},"s":[[1,0],[0,1]]}"#, // wrong rank
+        ] {
+            assert!(serde_json::from_str::<SecretKey>(bad).is_err(), "accepted {}", bad);
+        }
+    }
+
+    // Test armored and DER-like encodings of keys and ciphertexts
+    #[test]
+    pub fn test_pem_round_trip() {
+        use crate::types::{PublicKey, SecretKey, Ciphertext};
+
+        let params = Parameters::builder().n(256).q(7681).k(2).build().unwrap();
+        let (pk, sk) = keygen(&params, None);
+        let ct = encrypt(&pk.0, &pk.1, &[1, 1, 0, 1], &params, None);
+        let pk = PublicKey::new(&params, pk);
+        let sk = SecretKey::new(&params, sk);
+        let ct = Ciphertext::new(&params, ct);
+
+        assert_eq!(PublicKey::from_pem(&pk.to_pem()).unwrap(), pk);
+        assert_eq!(SecretKey::from_pem(&sk.to_pem()).unwrap(), sk);
+        assert_eq!(Ciphertext::from_pem(&ct.to_pem()).unwrap(), ct);
+        assert_eq!(PublicKey::from_der(&pk.to_der()).unwrap(), pk);
+        assert_eq!(SecretKey::from_der(&sk.to_der()).unwrap(), sk);
+        assert_eq!(Ciphertext::from_der(&ct.to_der()).unwrap(), ct);
+
+        // base64 lines are wrapped at 64 characters
+        assert!(pk.to_pem().lines().all(|line| line.len() <= 64));
+        // 13-bit coefficients take 2 bytes each
+        assert!(sk.to_der().len() < 2 * params.k() * params.n() + 32);
+    }
+
+    // Test that malformed armored and DER-like encodings are rejected
+    #[test]
+    pub fn test_pem_rejects_malformed() {
+        use crate::types::{PublicKey, SecretKey};
+
+        let params = Parameters::builder().n(16).q(97).k(2).build().unwrap();
+        let (pk, sk) = keygen(&params, None);
+        let pk = PublicKey::new(&params, pk);
+        let sk = SecretKey::new(&params, sk);
+        let pem = sk.to_pem();
+
+        // wrong label, mismatched header, missing footer
+        assert!(PublicKey::from_pem(&pem).is_err());
+        assert!(SecretKey::from_pem(&pem.replace("k: 2", "k: 3")).is_err());
+        assert!(SecretKey::from_pem(pem.trim_end().trim_end_matches("-----END MODULE-LWE SECRET KEY-----")).is_err());
+
+        // a public key is not a secret key, and truncated or extended data is rejected
+        let der = sk.to_der();
+        assert!(SecretKey::from_der(&pk.to_der()).is_err());
+        assert!(SecretKey::from_der(&der[..der.len() - 1]).is_err());
+        let mut extended = der.clone();
+        extended.push(0);
+        assert!(SecretKey::from_der(&extended).is_err());
+        // coefficient 97 is out of range for q = 97
+        let mut out_of_range = der.clone();
+        let last = out_of_range.len() - 1;
+        out_of_range[last] = 97;
+        assert!(SecretKey::from_der(&out_of_range).is_err());
+    }
+
+    // Test that typed keys re-encode to the legacy keygen_string format
+    #[test]
+    pub fn test_typed_keys_base64() {
+        use crate::types::{PublicKey, SecretKey};
+
+        let params = Parameters::builder().n(256).q(7681).k(2).build().unwrap();
+        let keys = keygen_string(&params, Some(7));
+        let (pk, sk) = keygen(&params, Some(7));
+        assert_eq!(&PublicKey::new(&params, pk).to_base64(), keys.get("public").unwrap());
+        assert_eq!(&SecretKey::new(&params, sk).to_base64(), keys.get("secret").unwrap());
+    }
+
+    // Test that passphrase-protected secret keys round trip and reject wrong passphrases and tampering
+    #[cfg(feature = "keystore")]
+    #[test]
+    pub fn test_encrypted_secret_key() {
+        use crate::types::SecretKey;
+        use crate::keystore::{KdfParams, is_encrypted_pem};
+
+        let params = Parameters::builder().n(256).q(7681).k(2).build().unwrap();
+        let (_, sk) = keygen(&params, None);
+        let sk = SecretKey::new(&params, sk);
+        let kdf = KdfParams { log_n: 8, r: 8, p: 1 };
+        let pem = sk.to_encrypted_pem_with(b"hunter2", &kdf).unwrap();
+        assert!(is_encrypted_pem(&pem));
+        assert!(!is_encrypted_pem(&sk.to_pem()));
+        assert_eq!(SecretKey::from_encrypted_pem(&pem, b"hunter2").unwrap(), sk);
+        // a fresh salt and nonce are drawn every time
+        assert_ne!(sk.to_encrypted_pem_with(b"hunter2", &kdf).unwrap(), pem);
+
+        assert_eq!(SecretKey::from_encrypted_pem(&pem, b"hunter3"), Err(Error::Authentication));
+        assert_eq!(SecretKey::from_encrypted_pem(&pem.replace("log-n: 8", "log-n: 9"), b"hunter2"), Err(Error::Authentication));
+        assert!(matches!(SecretKey::from_encrypted_pem(&pem.replace("log-n: 8", "log-n: 40"), b"hunter2"), Err(Error::InvalidParameters(_))));
+        assert!(SecretKey::from_encrypted_pem(&pem.replace("kdf: scrypt", "kdf: none"), b"hunter2").is_err());
+        assert!(SecretKey::from_encrypted_pem(&sk.to_pem(), b"hunter2").is_err());
+        // flipping a character of the sealed body breaks the authentication tag
+        let body_line = pem.lines().nth(8).unwrap();
+        let flipped: String = body_line.chars().enumerate().map(|(i, c)| if i == 0 { if c == 'A' { 'B' } else { 'A' } } else { c }).collect();
+        assert_eq!(SecretKey::from_encrypted_pem(&pem.replace(body_line, &flipped), b"hunter2"), Err(Error::Authentication));
+    }
+
+    // Replay the recorded known-answer files so any change to keygen, sampling, encoding or serialization is caught
+    #[test]
+    pub fn test_known_answers() {
+        use crate::kat::{generate, parse, verify};
+        use crate::utils::PRESETS;
+
+        let files = [
+            ("toy", include_str!("../kat/toy.rsp")),
+            ("small", include_str!("../kat/small.rsp")),
+            ("default", include_str!("../kat/default.rsp")),
+        ];
+        assert_eq!(files.len(), PRESETS.len());
+        for (name, kat) in files {
+            let (params, entries) = parse(kat).unwrap();
+            assert_eq!(Parameters::preset(name), Some(params));
+            assert!(!entries.is_empty());
+            assert_eq!(verify(kat), Ok(entries.len()), "{}", name);
+        }
+
+        // a regenerated file is identical, and a changed byte is reported
+        let toy = files[0].1;
+        assert_eq!(generate("toy", &Parameters::preset("toy").unwrap(), 10), toy);
+        let ct_line = toy.lines().find(|line| line.starts_with("ct = ")).unwrap();
+        let tampered = toy.replacen(ct_line, &ct_line.replacen("ct = Y", "ct = Z", 1), 1);
+        assert!(matches!(verify(&tampered), Err(Error::KnownAnswer(_))));
+        let pk_line = toy.lines().find(|line| line.starts_with("pk = ")).unwrap();
+        assert!(verify(&toy.replacen(pk_line, "pk = 00", 1)).is_err());
+
+        // every seeded sample is drawn from its own seed
+        let params = mul_params();
+        let (pk, sk) = keygen(&params, Some(33));
+        assert_ne!(pk.0[0][0], pk.0[0][1]);
+        assert_ne!(key_error(&pk, &sk, &params), sk);
+    }
+
+    // Test that the decoding and decryption entry points return errors rather than panicking on malformed input
+    #[test]
+    pub fn test_malformed_inputs_do_not_panic() {
+        use rand::{Rng, SeedableRng};
+        use rand::rngs::StdRng;
+        use polynomial_ring::Polynomial;
+        use crate::utils::{compress, decompress};
+        use crate::decrypt::try_decrypt;
+        use crate::types::{PublicKey, SecretKey, Ciphertext};
+
+        let params = Parameters::preset("toy").unwrap();
+        let (n, q, k) = (params.n(), params.q(), params.k());
+        let keys = keygen_string(&params, Some(1));
+        let (pk_string, sk_string) = (keys.get("public").unwrap(), keys.get("secret").unwrap());
+        let ct_string = encrypt_string(pk_string, "hi", &params, Some(2)).unwrap();
+
+        assert!(decompress("not base64!").is_err());
+        assert!(decompress("AAAA").is_err());
+        assert!(encrypt_string(&compress(&vec![0; (k * k + k) * n - 1]), "hi", &params, None).is_err());
+        assert!(encrypt_string(&compress(&vec![q; (k * k + k) * n]), "hi", &params, None).is_err());
+        assert!(decrypt_string(&compress(&vec![0; k * n + 1]), &ct_string, &params).is_err());
+        assert!(decrypt_string(&compress(&vec![i64::MAX; k * n]), &ct_string, &params).is_err());
+        assert!(decrypt_string(sk_string, &compress(&vec![0; (k + 1) * n + 1]), &params).is_err());
+        assert!(decrypt_string(sk_string, &compress(&vec![i64::MIN; (k + 1) * n]), &params).is_err());
+        assert_eq!(decrypt_string(sk_string, &ct_string, &params), Ok(String::from("hi")));
+        assert!(Parameters::builder().n(1 << 62).build().is_err());
+
+        let mut rng = StdRng::seed_from_u64(34);
+        let valid = [pk_string.clone(), sk_string.clone(), ct_string.clone()];
+        for _ in 0..200 {
+            // random coefficient vectors of plausible and implausible lengths and sizes
+            let len = [rng.gen_range(0..4 * n), k * n, (k + 1) * n, (k * k + k) * n][rng.gen_range(0..4)];
+            let bound = [2, q, i64::MAX][rng.gen_range(0..3)];
+            let coeffs: Vec<i64> = (0..len).map(|_| rng.gen_range(-bound..bound)).collect();
+            let _ = encrypt_string(&compress(&coeffs), "hi", &params, Some(0));
+            let _ = decrypt_string(&compress(&coeffs), &ct_string, &params);
+            let _ = decrypt_string(sk_string, &compress(&coeffs), &params);
+
+            // corrupted copies of valid strings
+            let mut bytes = valid[rng.gen_range(0..3)].clone().into_bytes();
+            for _ in 0..rng.gen_range(1..4) {
+                let i = rng.gen_range(0..bytes.len());
+                bytes[i] = rng.gen_range(b'+'..=b'z');
+            }
+            bytes.truncate(rng.gen_range(0..=bytes.len()));
+            let corrupted = String::from_utf8(bytes).unwrap();
+            let _ = decompress(&corrupted);
+            let _ = encrypt_string(&corrupted, "hi", &params, Some(0));
+            let _ = decrypt_string(&corrupted, &ct_string, &params);
+            let _ = decrypt_string(sk_string, &corrupted, &params);
+            let _ = PublicKey::from_pem(&corrupted);
+            let _ = crate::kat::parse(&corrupted);
+
+            // ciphertexts and keys of arbitrary shape
+            let random_vec = |rng: &mut StdRng| -> Vec<Polynomial<i64>> {
+                (0..rng.gen_range(0..k + 2))
+                    .map(|_| Polynomial::new((0..rng.gen_range(0..2 * n)).map(|_| rng.gen_range(-bound..bound)).collect()))
+                    .collect()
+            };
+            let (sk, u) = (random_vec(&mut rng), random_vec(&mut rng));
+            let v = random_vec(&mut rng).pop().unwrap_or_else(|| Polynomial::new(vec![]));
+            let _ = try_decrypt(&sk, &u, &v, &params);
+
+            // random DER
+            let der: Vec<u8> = (0..rng.gen_range(0..64)).map(|_| rng.gen()).collect();
+            let _ = (PublicKey::from_der(&der), SecretKey::from_der(&der), Ciphertext::from_der(&der));
+        }
+    }
+
+    // Test that the measured failure rate and noise variance agree with the analytic estimates
+    // n = 16, q = 97, k = 4 fails about 2% of the time, so a few thousand trials see dozens of failures
+    #[test]
+    pub fn test_failure_rate_measurement() {
+        use crate::failure::{measure_failure_rate, FailureRateConfig};
+
+        let params = Parameters::builder().n(16).q(97).k(4).build().unwrap();
+        // seeded, so that the statistical checks below cannot fail by chance
+        let config = FailureRateConfig { trials: 4000, threads: 3, trials_per_key: 50, seed: Some(36) };
+        let report = measure_failure_rate(&params, &config);
+        assert_eq!(report.trials, 4000);
+        assert_eq!(report.histogram.values().sum::<u64>(), 4000 * 16);
+        assert_eq!(report.coefficient_failures.len(), 16);
+        let coefficient_failures: u64 = report.coefficient_failures.iter().sum();
+        assert!(coefficient_failures >= report.failures);
+        assert!(report.failures > 0);
+
+        let ratio = report.failure_rate() / report.analytic_failure_rate();
+        assert!((0.5..2.0).contains(&ratio), "measured {} analytic {}", report.failure_rate(), report.analytic_failure_rate());
+        let ratio = report.empirical_variance() / report.analytic_variance;
+        assert!((0.9..1.1).contains(&ratio), "measured {} analytic {}", report.empirical_variance(), report.analytic_variance);
+
+        // with half_q = 49 noise of magnitude 25 or more always fails, 23 or less never does, and 24 depends on the bit
+        let beyond = |bound: i64| -> u64 { report.histogram.iter().filter(|(e, _)| e.abs() >= bound).map(|(_, c)| c).sum() };
+        assert!((beyond(25)..=beyond(24)).contains(&coefficient_failures));
+        assert!(report.histogram_csv().starts_with("noise,count\n"));
+        assert_eq!(report.coefficients_csv().lines().count(), 17);
+    }
+
+    // Test the lattice reduction primitives and the primal attack on toy parameters
+    #[test]
+    pub fn test_primal_attack() {
+        use polynomial_ring::Polynomial;
+        use crate::attacks::{lll, bkz, norm_sq, rotation_matrix, to_plain_lwe, primal_attack};
+        use crate::utils::mul_poly;
+
+        // a skewed basis of Z^3 reduces to unit vectors
+        let mut basis = vec![vec![1, 0, 0], vec![4, 1, 0], vec![15, 7, 1]];
+        lll(&mut basis, 0.99);
+        assert!(basis.iter().all(|b| norm_sq(b) == 1));
+
+        // BKZ finds the shortest vector of a small knapsack-style lattice at least as well as LLL
+        let rows: Vec<Vec<i64>> = (0..6).map(|i| {
+            let mut row = vec![0; 7];
+            row[i] = 1;
+            row[6] = [1021, 877, 631, 409, 263, 149][i];
+            row
+        }).collect();
+        let (mut reduced_lll, mut reduced_bkz) = (rows.clone(), rows.clone());
+        lll(&mut reduced_lll, 0.99);
+        bkz(&mut reduced_bkz, 6, 4);
+        let shortest = |b: &Vec<Vec<i64>>| b.iter().map(|v| norm_sq(v)).min().unwrap();
+        assert!(shortest(&reduced_bkz) <= shortest(&reduced_lll));
+
+        // the rotation matrix multiplies in Z[x]/(x^n + 1)
+        let params = Parameters::builder().n(8).q(97).k(2).build().unwrap();
+        let (a, s) = (Polynomial::new(vec![3, -5, 7, 0, 1, 2, -1, 4]), Polynomial::new(vec![1, 0, -1, 1, 0, 0, 1, -1]));
+        let rot = rotation_matrix(&a, 8);
+        let mut product = mul_poly(&a, &s, params.q(), params.f(), params.omega()).coeffs().to_vec();
+        product.resize(8, 0);
+        let mut s_coeffs = s.coeffs().to_vec();
+        s_coeffs.resize(8, 0);
+        for (row, p) in rot.iter().zip(product.iter()) {
+            let dot: i64 = row.iter().zip(s_coeffs.iter()).map(|(x, y)| x * y).sum();
+            assert_eq!(dot.rem_euclid(97), p.rem_euclid(97));
+        }
+
+        // recover the secret from a toy public key
+        let (pk, sk) = keygen(&params, None);
+        let (a_plain, t_plain) = to_plain_lwe(&pk.0, &pk.1, &params);
+        assert_eq!((a_plain.len(), t_plain.len()), (16, 16));
+        let recovered = primal_attack(&pk, &params, 10).expect("toy parameters are broken");
+        assert_eq!(recovered.len(), sk.len());
+        for (r, s) in recovered.iter().zip(sk.iter()) {
+            assert_eq!(r, s);
+        }
+    }
+
+    // Test that a decryption oracle leaks the secret key to crafted ciphertexts
+    #[test]
+    pub fn test_cca_key_recovery() {
+        use polynomial_ring::Polynomial;
+        use crate::cca::recover_secret_key;
+        use crate::decrypt::try_decrypt;
+
+        for params in [Parameters::default(), Parameters::preset("toy").unwrap()] {
+            let (_, sk) = keygen(&params, None);
+            let recovery = recover_secret_key(&params, 1, |u: &[Polynomial<i64>], v: &Polynomial<i64>| {
+                Some(decrypt(&sk, u, v, &params))
+            }).expect("the CPA scheme answers every query");
+            assert_eq!(recovery.sk, sk);
+            // a binary search for the threshold, then two queries per secret polynomial
+            assert!(recovery.queries <= 2 + 16 + 2 * params.k());
+
+            // shape and range checks alone do not stop the attack
+            let recovery = recover_secret_key(&params, 1, |u: &[Polynomial<i64>], v: &Polynomial<i64>| {
+                try_decrypt(&sk, u, v, &params).ok()
+            }).unwrap();
+            assert_eq!(recovery.sk, sk);
+        }
+
+        // an oracle that rejects crafted ciphertexts defeats it
+        let params = Parameters::default();
+        assert_eq!(recover_secret_key(&params, 1, |_: &[Polynomial<i64>], _: &Polynomial<i64>| None), None);
+    }
+
+    // Test the plain LWE instance export
+    #[test]
+    pub fn test_instance_export() {
+        use crate::export::{LweInstance, npy};
+
+        let params = Parameters::preset("toy").unwrap();
+        let (pk, sk) = keygen(&params, None);
+        let instance = LweInstance::new(&pk, Some(&sk), &params);
+        let (dim, q) = (instance.dimension(), params.q());
+        assert_eq!(dim, 32);
+
+        // t - A s is the ternary error
+        let s = instance.s.as_ref().unwrap();
+        for (row, t) in instance.a.iter().zip(instance.t.iter()) {
+            assert!(row.iter().all(|&c| (0..q).contains(&c)));
+            let e = (t - row.iter().zip(s.iter()).map(|(a, s)| a * s).sum::<i64>()).rem_euclid(q);
+            assert!(e <= 1 || e == q - 1);
+        }
+
+        // text formats
+        let sage = instance.to_sage();
+        assert!(sage.contains("n, q, k = 16, 193, 2\n"));
+        assert_eq!(sage.matches("\n    [").count(), dim);
+        assert!(sage.contains("s = vector(ZZ"));
+        let fplll = instance.a_fplll();
+        assert!(fplll.starts_with("[[") && fplll.ends_with("]\n]\n"));
+        assert_eq!(fplll.lines().count(), dim + 1);
+        assert_eq!(instance.t_fplll().lines().count(), 2);
+        let metadata = instance.metadata_json();
+        assert!(metadata.contains("\"q\": 193") && metadata.contains("\"distribution\": \"ternary\""));
+        assert!(instance.estimator().contains("LWE.Parameters(n=32, q=193, Xs=ND.Uniform(-1, 1)"));
+
+        // .npy files are 64-byte aligned little-endian arrays
+        let bytes = npy(&[2, 3], &[1, -2, 3, 4, 5, 6]);
+        let header_len = u16::from_le_bytes([bytes[8], bytes[9]]) as usize;
+        assert_eq!((10 + header_len) % 64, 0);
+        assert!(std::str::from_utf8(&bytes[10..10 + header_len]).unwrap().contains("'shape': (2, 3)"));
+        assert_eq!(&bytes[10 + header_len + 8..10 + header_len + 16], &(-2i64).to_le_bytes());
+        assert!(String::from_utf8_lossy(&npy(&[3], &[0, 0, 0])).contains("'shape': (3,)"));
+
+        let dir = std::env::temp_dir().join(format!("module-lwe-export-{}", std::process::id()));
+        let paths = instance.write(&dir).unwrap();
+        assert_eq!(paths.len(), 7);
+        assert_eq!(std::fs::read(dir.join("A.npy")).unwrap().len(), 128 + dim * dim * 8);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    // Test the signature scheme on every preset against recorded known answers
+    #[test]
+    pub fn test_signatures() {
+        use polynomial_ring::Polynomial;
+        use sha3::Shake256;
+        use sha3::digest::{Update, ExtendableOutput, XofReader};
+        use crate::sign::{SignParameters, Signature, SIGN_PRESETS, SIGN_Q, keygen, sign, verify, decompose};
+
+        // high and low bits recombine, with low bits in (-gamma2, gamma2]
+        let alpha = (SIGN_Q - 1) / 16;
+        for r in (0..SIGN_Q).step_by(9973).chain([alpha / 2, alpha / 2 + 1, SIGN_Q - 1]) {
+            let (r1, r0) = decompose(r, alpha, SIGN_Q);
+            assert_eq!((r1 * alpha + r0).rem_euclid(SIGN_Q), r);
+            assert!(-alpha / 2 <= r0 && r0 <= alpha / 2 && (0..16).contains(&r1));
+        }
+
+        // SHAKE256 digests of the signature of "module-lwe" under the key generated from seed 40, signed with seed 41
+        let known_answers = [
+            ("toy", "a4c45a5e66de3fd5d4ce7b2fc7318c27"),
+            ("level2", "a1c573324842b25aec8ecaf2afdd54f3"),
+            ("level3", "9b3f93ff8a043707cadbc5e37dd401d7"),
+        ];
+        assert_eq!(known_answers.len(), SIGN_PRESETS.len());
+        for (name, digest) in known_answers {
+            let sign_params = SignParameters::preset(name).unwrap();
+            let (vk, sk) = keygen(&sign_params, Some(40));
+            let signature = sign(&sk, b"module-lwe", &sign_params, Some(41)).unwrap();
+            assert!(verify(&vk, b"module-lwe", &signature, &sign_params));
+            assert_eq!(Ok(signature.clone()), sign(&sk, b"module-lwe", &sign_params, Some(41)));
+
+            let bytes = signature.to_bytes(&sign_params);
+            assert_eq!(Signature::from_bytes(&bytes, &sign_params), Ok(signature.clone()));
+            assert!(Signature::from_bytes(&bytes[1..], &sign_params).is_err());
+            let mut hasher = Shake256::default();
+            hasher.update(&bytes);
+            let mut out = [0u8; 16];
+            hasher.finalize_xof().read(&mut out);
+            let hex: String = out.iter().map(|b| format!("{:02x}", b)).collect();
+            assert_eq!(hex, digest, "{}", name);
+
+            // the response hides s1 and every tampering is rejected
+            let bound = sign_params.gamma1() - sign_params.beta();
+            assert!(signature.z.iter().flat_map(|p| p.coeffs()).all(|c| c.abs() < bound));
+            assert!(!verify(&vk, b"module-lwf", &signature, &sign_params));
+            let mut forged = signature.clone();
+            forged.c_tilde[0] ^= 1;
+            assert!(!verify(&vk, b"module-lwe", &forged, &sign_params));
+            let mut forged = signature.clone();
+            forged.z[0] = Polynomial::new(vec![bound]);
+            assert!(!verify(&vk, b"module-lwe", &forged, &sign_params));
+            let (other_vk, _) = keygen(&sign_params, Some(42));
+            assert!(!verify(&other_vk, b"module-lwe", &signature, &sign_params));
+        }
+
+        // fresh randomness still verifies
+        let sign_params = SignParameters::preset("toy").unwrap();
+        let (vk, sk) = keygen(&sign_params, None);
+        for msg in [&b""[..], b"a", &[0xff; 100]] {
+            assert!(verify(&vk, msg, &sign(&sk, msg, &sign_params, None).unwrap(), &sign_params));
+        }
+
+        // parameters that reject almost every mask give up instead of looping
+        let strict = SignParameters::new(sign_params.params().clone(), 1, 1, 2, sign_params.gamma2()).unwrap();
+        let (_, sk) = keygen(&strict, Some(40));
+        assert!(matches!(sign(&sk, b"module-lwe", &strict, Some(41)), Err(Error::InvalidParameters(_))));
+        // the challenge signs come from one 64-bit word
+        let wide = Parameters::builder().n(128).q(SIGN_Q).k(2).build().unwrap();
+        assert!(SignParameters::new(wide.clone(), 1, 64, 1 << 17, sign_params.gamma2()).is_ok());
+        assert!(SignParameters::new(wide, 1, 65, 1 << 17, sign_params.gamma2()).is_err());
+    }
+
+    // Test 3-of-5 threshold decryption with every party run in-process
+    #[test]
+    pub fn test_threshold_decryption() {
+        use crate::threshold::{share_secret_key, reconstruct_secret_key, partial_decrypt, combine, lagrange_coefficients};
+
+        let params = Parameters::default();
+        let n = params.n;
+        let (pk, sk) = keygen(&params, None);
+        let shares = share_secret_key(&sk, 3, 5, &params, None).unwrap();
+        assert_eq!(shares.iter().map(|s| s.index).collect::<Vec<_>>(), vec![1, 2, 3, 4, 5]);
+        assert!(shares.iter().all(|s| s.sk != sk));
+
+        // any t shares reconstruct the key, t - 1 do not
+        assert_eq!(reconstruct_secret_key(&[shares[4].clone(), shares[0].clone(), shares[2].clone()], &params).unwrap(), sk);
+        assert_ne!(reconstruct_secret_key(&shares[..2], &params).unwrap(), sk);
+
+        let mut m_b: Vec<i64> = (0..n as i64).map(|i| (i * 7 + 3) % 5 % 2).collect();
+        m_b[n - 1] = 1;
+        let (u, v) = encrypt(&pk.0, &pk.1, &m_b, &params, None);
+        let run = |participants: &[i64]| -> Vec<i64> {
+            let partials: Vec<_> = participants.iter()
+                .map(|&i| partial_decrypt(&shares[i as usize - 1], participants, &u, 256, &params, None).unwrap())
+                .collect();
+            // no single partial decryption reveals the message
+            assert!(partials.iter().all(|p| p.d != mul_vec_simple(&sk, &u, params.q, &params.f, params.omega)));
+            combine(&partials, &v, &params).unwrap()
+        };
+        assert_eq!(run(&[1, 3, 5]), m_b);
+        assert_eq!(run(&[2, 3, 4]), m_b);
+        assert_eq!(run(&[1, 2, 3, 4, 5]), m_b);
+        assert_ne!(run(&[1, 2]), m_b);
+
+        // malformed inputs
+        assert!(share_secret_key(&sk, 0, 5, &params, None).is_err());
+        assert!(share_secret_key(&sk, 6, 5, &params, None).is_err());
+        assert!(lagrange_coefficients(&[1, 2, 1], params.q).is_err());
+        assert!(partial_decrypt(&shares[0], &[2, 3, 4], &u, 256, &params, None).is_err());
+        assert!(combine(&[], &v, &params).is_err());
+        let partial = partial_decrypt(&shares[0], &[1, 2, 3], &u, 256, &params, None).unwrap();
+        assert!(combine(&[partial.clone(), partial], &v, &params).is_err());
+    }
+
+    // Test distributed key generation with every party on its own thread, talking over channels
+    #[test]
+    pub fn test_distributed_keygen() {
+        use std::sync::mpsc::channel;
+        use polynomial_ring::Polynomial;
+        use crate::dkg::{shared_matrix, joint_public_key, Party, PublicShare, ShareCommitment, Message};
+        use crate::threshold::combine;
+
+        // messages cross the channels serialized when serde is available
+        #[cfg(feature = "serde")]
+        fn wire(msg: Message) -> Message {
+            serde_json::from_str(&serde_json::to_string(&msg).unwrap()).unwrap()
+        }
+        #[cfg(not(feature = "serde"))]
+        fn wire(msg: Message) -> Message {
+            msg
+        }
+
+        let params = Parameters::default();
+        let n = params.n;
+        let a = shared_matrix(&params, 2025);
+        assert_eq!(a, shared_matrix(&params, 2025));
+        assert_ne!(a[0][0], a[0][1]);
+
+        let (to_coordinator, from_parties) = channel();
+        let mut to_parties = vec![];
+        let mut reveal = vec![];
+        let handles: Vec<_> = (1..=3).map(|index| {
+            let (to_party, from_coordinator) = channel::<Vec<Polynomial<i64>>>();
+            let (to_reveal, from_reveal) = channel::<()>();
+            to_parties.push(to_party);
+            reveal.push(to_reveal);
+            let (to_coordinator, a, params) = (to_coordinator.clone(), a.clone(), params.clone());
+            std::thread::spawn(move || {
+                let party = Party::new(index, &a, &params, None).unwrap();
+                // the share is revealed only after every party has committed
+                to_coordinator.send(wire(party.commitment())).unwrap();
+                from_reveal.recv().unwrap();
+                to_coordinator.send(wire(party.public_share())).unwrap();
+                // answer every ciphertext until the coordinator hangs up
+                for u in from_coordinator {
+                    to_coordinator.send(wire(party.partial_decrypt(&u, 256, None).unwrap())).unwrap();
+                }
+            })
+        }).collect();
+
+        let commitments: Vec<ShareCommitment> = (0..3).map(|_| match from_parties.recv().unwrap() {
+            Message::Commitment(commitment) => commitment,
+            msg => panic!("unexpected message {:?}", msg),
+        }).collect();
+        for to_reveal in reveal.iter() {
+            to_reveal.send(()).unwrap();
+        }
+        let shares: Vec<PublicShare> = (0..3).map(|_| match from_parties.recv().unwrap() {
+            Message::PublicShare(share) => share,
+            msg => panic!("unexpected message {:?}", msg),
+        }).collect();
+        let pk = joint_public_key(&a, &commitments, &shares, &params).unwrap();
+
+        let mut m_b: Vec<i64> = (0..n as i64).map(|i| i % 3 % 2).collect();
+        m_b[n - 1] = 1;
+        let (u, v) = encrypt(&pk.0, &pk.1, &m_b, &params, None);
+        for to_party in to_parties.iter() {
+            to_party.send(u.clone()).unwrap();
+        }
+        let partials: Vec<_> = (0..3).map(|_| match from_parties.recv().unwrap() {
+            Message::PartialDecryption(partial) => partial,
+            msg => panic!("unexpected message {:?}", msg),
+        }).collect();
+        drop(to_parties);
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        // every party is needed
+        assert_eq!(combine(&partials, &v, &params).unwrap(), m_b);
+        assert_ne!(combine(&partials[..2], &v, &params).unwrap(), m_b);
+
+        // malformed shares are rejected
+        assert!(joint_public_key(&a, &[], &[], &params).is_err());
+        assert!(joint_public_key(&a, &commitments[..2], &shares, &params).is_err());
+        let twice = [shares[0].clone(), shares[0].clone()];
+        assert!(joint_public_key(&a, &[twice[0].commitment(), twice[1].commitment()], &twice, &params).is_err());
+        let short = PublicShare { index: 4, t: shares[0].t[1..].to_vec() };
+        let pair = [shares[1].clone(), short];
+        assert!(joint_public_key(&a, &[pair[0].commitment(), pair[1].commitment()], &pair, &params).is_err());
+        assert!(Party::new(0, &a, &params, None).is_err());
+
+        // a share chosen after seeing the others no longer matches its commitment
+        let mut rogue = shares.clone();
+        let index = rogue[2].index;
+        rogue[2] = PublicShare { index, t: pk.1.iter().map(|p| ring_lwe::utils::polyinv(p, params.q)).collect() };
+        assert!(matches!(joint_public_key(&a, &commitments, &rogue, &params), Err(Error::InvalidParameters(_))));
+    }
+
+    // Test proxy re-encryption and the noise it adds per hop
+    #[test]
+    pub fn test_proxy_reencryption() {
+        use polynomial_ring::Polynomial;
+        use crate::proxy::{reencryption_keygen, reencrypt, reencryption_noise_bound};
+
+        let params = mul_params();
+        let (n, q) = (params.n, params.q);
+        let base = 1 << 10;
+        let (pk_a, sk_a) = keygen(&params, None);
+        let (pk_b, sk_b) = keygen(&params, None);
+        let (pk_c, sk_c) = keygen(&params, None);
+        let rk_ab = reencryption_keygen(&sk_a, &pk_b, base, &params, None);
+        let rk_bc = reencryption_keygen(&sk_b, &pk_c, base, &params, None);
+
+        let m_b: Vec<i64> = (0..n as i64).map(|i| (i * 5 + 1) % 3 % 2).collect();
+        let ct = encrypt(&pk_a.0, &pk_a.1, &m_b, &params, None);
+        let max_noise = |sk: &[Polynomial<i64>], ct: &(Vec<Polynomial<i64>>, Polynomial<i64>)| -> i64 {
+            measure_noise(sk, &ct.0, &ct.1, &m_b, &params).iter().map(|e| e.abs()).max().unwrap()
+        };
+        let fresh = max_noise(&sk_a, &ct);
+
+        // one hop stays within the worst-case bound, which leaves plenty of room below q/4
+        let bound = reencryption_noise_bound(base, &params);
+        assert!(fresh + bound < q / 4);
+        let ct_b = reencrypt(&ct, &rk_ab, &params);
+        assert_eq!(decrypt_full(&sk_b, &ct_b, &params), m_b);
+        let noise_b = max_noise(&sk_b, &ct_b);
+        assert!(noise_b <= fresh + bound);
+        assert!(noise_b > fresh);
+
+        // the delegator's key no longer decrypts, and the key only works for the ciphertexts of its delegator
+        assert!(max_noise(&sk_a, &ct_b) > q / 8);
+        let other = encrypt(&pk_c.0, &pk_c.1, &m_b, &params, None);
+        assert!(max_noise(&sk_b, &reencrypt(&other, &rk_ab, &params)) > q / 8);
+
+        // a second hop adds at most another bound
+        let ct_c = reencrypt(&ct_b, &rk_bc, &params);
+        let noise_c = max_noise(&sk_c, &ct_c);
+        assert!(noise_c <= noise_b + bound);
+        assert_eq!(decrypt_full(&sk_c, &ct_c, &params), m_b);
+
+        // the default modulus is too small for even one hop
+        let default = Parameters::default();
+        assert!((2..=1 << 12).all(|base| reencryption_noise_bound(base, &default) >= default.q / 4));
+    }
+
+    // Test encrypting one message to several keys that share a public matrix
+    #[test]
+    pub fn test_multi_recipient() {
+        use crate::keygen::keygen_with_matrix;
+        use crate::multi::{encrypt_multi, decrypt_multi};
+
+        let params = Parameters::default();
+        let n = params.n;
+        let (pk0, sk0) = keygen(&params, Some(44));
+        let mut pks = vec![pk0.clone()];
+        let mut sks = vec![sk0];
+        for i in 1..4 {
+            let (pk, sk) = keygen_with_matrix(&pk0.0, &params, Some(44 + i));
+            pks.push(pk);
+            sks.push(sk);
+        }
+        // consecutive seeds give unrelated secrets, each independent of its error
+        for (i, (pk, sk)) in pks.iter().zip(sks.iter()).enumerate() {
+            assert_ne!(key_error(pk, sk, &params), *sk);
+            assert!(sks[..i].iter().all(|other| other != sk));
+        }
+        let m_b: Vec<i64> = (0..n as i64).map(|i| (i * 7 + 3) % 5 % 2).collect();
+        let ct = encrypt_multi(&pks, &m_b, &params, Some(7)).unwrap();
+        assert_eq!(ct.len(), 4);
+
+        // every recipient decrypts with the dedicated API and with the ordinary one
+        for (i, sk) in sks.iter().enumerate() {
+            assert_eq!(decrypt_multi(sk, &ct, i, &params).unwrap(), m_b);
+            assert_eq!(decrypt_full(sk, &ct.recipient(i).unwrap(), &params), m_b);
+        }
+        // fresh noise per recipient keeps the v's distinct even for equal keys
+        let twice = encrypt_multi(&[pks[0].clone(), pks[0].clone()], &m_b, &params, Some(7)).unwrap();
+        assert_ne!(twice.v[0], twice.v[1]);
+
+        // seeded encryption is deterministic
+        assert_eq!(encrypt_multi(&pks, &m_b, &params, Some(7)).unwrap(), ct);
+
+        // keys with a different matrix, no keys and unknown recipients are rejected
+        let (other, _) = keygen(&params, Some(99));
+        assert!(matches!(encrypt_multi(&[pks[0].clone(), other], &m_b, &params, None), Err(Error::InvalidParameters(_))));
+        assert!(encrypt_multi(&[], &m_b, &params, None).is_err());
+        assert!(encrypt_multi(&pks, &vec![1; n + 1], &params, None).is_err());
+        assert!(decrypt_multi(&sks[0], &ct, 4, &params).is_err());
+        assert!(ct.recipient(4).is_none());
+    }
+
+    // Test key generation and encryption against a common reference string
+    #[test]
+    pub fn test_crs_mode() {
+        use crate::crs::{Crs, keygen as crs_keygen, encrypt as crs_encrypt};
+        use crate::multi::encrypt_multi;
+
+        let params = Parameters::default();
+        let n = params.n;
+        let crs = Crs::generate(&params, Some(45));
+        // the same public seed gives the same CRS, and the identifier binds the parameters
+        assert_eq!(Crs::generate(&params, Some(45)), crs);
+        assert_ne!(Crs::generate(&params, Some(46)).id(), crs.id());
+        let small = Parameters::preset("small").unwrap();
+        assert_ne!(Crs::generate(&small, Some(45)).id(), crs.id());
+
+        // the CRS survives a round trip through base64 and through a full public key
+        let loaded = Crs::from_base64(&crs.to_base64(), &params).unwrap();
+        assert_eq!(loaded, crs);
+        let (pk, sk) = crs_keygen(&crs, Some(1));
+        let full = pk.to_public_key(&crs).unwrap();
+        assert_eq!(Crs::from_matrix(&full.0, &params).unwrap().id(), crs.id());
+
+        let m_b: Vec<i64> = (0..n as i64).map(|i| (i * 3 + 1) % 4 % 2).collect();
+        assert_eq!(decrypt_full(&sk, &crs_encrypt(&loaded, &pk, &m_b, None).unwrap(), &params), m_b);
+
+        // keys from one CRS can share a multi-recipient ciphertext
+        let (pk2, sk2) = crs_keygen(&crs, Some(2));
+        assert_ne!(sk2, sk);
+        assert_ne!(key_error(&pk2.to_public_key(&crs).unwrap(), &sk2, &params), sk2);
+        let pks = [pk.to_public_key(&crs).unwrap(), pk2.to_public_key(&crs).unwrap()];
+        let ct = encrypt_multi(&pks, &m_b, &params, None).unwrap();
+        assert_eq!(crate::multi::decrypt_multi(&sk2, &ct, 1, &params).unwrap(), m_b);
+
+        // a key is rejected by any other CRS, and malformed encodings are rejected
+        let other = Crs::generate(&params, Some(46));
+        assert!(matches!(crs_encrypt(&other, &pk, &m_b, None), Err(Error::InvalidParameters(_))));
+        assert!(pk.to_public_key(&other).is_err());
+        assert!(Crs::from_base64(&crs.to_base64(), &small).is_err());
+        assert!(Crs::from_base64("not a crs", &params).is_err());
+        assert!(Crs::from_matrix(&full.0[..1], &params).is_err());
+    }
+
+    // Test that rerandomized ciphertexts still decrypt and that their noise grows linearly
+    #[test]
+    pub fn test_rerandomization() {
+        use crate::homomorphic::rerandomize;
+
+        let params = Parameters::default();
+        let n = params.n;
+        let ternary = NoiseDistribution::Ternary;
+        let variance = noise_variance(&params, &ternary, &ternary);
+        let limit = max_rerandomizations(&params, &ternary, &ternary, -128.0).unwrap();
+        // the limit is tight: one more rerandomization misses the target
+        assert!(failure_probability_log2(&params, (limit + 1) as f64 * variance) <= -128.0);
+        assert!(failure_probability_log2(&params, (limit + 2) as f64 * variance) > -128.0);
+        assert_eq!(max_rerandomizations(&Parameters::preset("toy").unwrap(), &ternary, &ternary, -128.0), None);
+        assert_eq!(max_rerandomizations(&params, &ternary, &ternary, 0.0), None);
+        // a 30-bit modulus allows about 10^13 rerandomizations, found without stepping through them
+        let large = mul_params();
+        let large_variance = noise_variance(&large, &ternary, &ternary);
+        let large_limit = max_rerandomizations(&large, &ternary, &ternary, -128.0).unwrap();
+        assert!(large_limit > 1_000_000_000_000);
+        assert!(failure_probability_log2(&large, (large_limit + 1) as f64 * large_variance) <= -128.0);
+        assert!(failure_probability_log2(&large, (large_limit + 2) as f64 * large_variance) > -128.0);
+
+        // seeded, so that the statistical check on the noise cannot fail by chance
+        let mut seeds = crate::utils::SeedStream::new(Some(46));
+        let (pk, sk) = keygen(&params, seeds.next_seed());
+        let m_b: Vec<i64> = (0..n as i64).map(|i| (i * 11 + 2) % 7 % 2).collect();
+        let mut ct = encrypt(&pk.0, &pk.1, &m_b, &params, seeds.next_seed());
+        let mean_square = |ct: &(Vec<polynomial_ring::Polynomial<i64>>, polynomial_ring::Polynomial<i64>)| -> f64 {
+            let noise = measure_noise(&sk, &ct.0, &ct.1, &m_b, &params);
+            noise.iter().map(|&e| (e * e) as f64).sum::<f64>() / n as f64
+        };
+        for j in 1..=limit {
+            let next = rerandomize(&pk, &ct, &params, seeds.next_seed());
+            // fresh randomness changes every component of the ciphertext but not the message
+            assert!(next.0.iter().zip(ct.0.iter()).all(|(a, b)| a != b));
+            assert_ne!(next.1, ct.1);
+            assert_eq!(decrypt_full(&sk, &next, &params), m_b);
+            ct = next;
+            // the noise variance grows linearly, one fresh ciphertext per step
+            let expected = (j + 1) as f64 * variance;
+            let measured = mean_square(&ct);
+            assert!(measured > 0.7 * expected && measured < 1.3 * expected, "step {}: {} vs {}", j, measured, expected);
+        }
+    }
+
+    // Test that sanitization decrypts and that the smudging noise swamps the input noise
+    #[test]
+    pub fn test_sanitize() {
+        use crate::homomorphic::{add, sanitize, smudging_bound, max_sanitization_security};
+
+        let params = mul_params();
+        let (n, q, k) = (params.n, params.q, params.k);
+        let fresh = (2 * k * n + 1) as i64;
+        // seeded, so that the statistical check on the smudging noise cannot fail by chance
+        let mut seeds = crate::utils::SeedStream::new(Some(47));
+        let (pk, sk) = keygen(&params, seeds.next_seed());
+        let messages: Vec<Vec<i64>> = (0..4).map(|j| (0..n as i64).map(|i| (i + j) % 3 % 2).collect()).collect();
+        let mut ct = encrypt(&pk.0, &pk.1, &messages[0], &params, seeds.next_seed());
+        let mut m_b = messages[0].clone();
+        for msg in &messages[1..] {
+            ct = add(&ct, &encrypt(&pk.0, &pk.1, msg, &params, seeds.next_seed()), &params);
+            m_b = m_b.iter().zip(msg).map(|(a, b)| (a + b) % 2).collect();
+        }
+        let noise_bound = 4 * fresh;
+        let input_noise = measure_noise(&sk, &ct.0, &ct.1, &m_b, &params).iter().map(|e| e.abs()).max().unwrap();
+        assert!(input_noise <= noise_bound);
+
+        // the largest admissible lambda decrypts, and the smudging noise swamps the input noise
+        let lambda = max_sanitization_security(noise_bound, &params).unwrap();
+        assert_eq!(lambda, 16);
+        let bound = smudging_bound(noise_bound, lambda, &params).unwrap();
+        assert!(noise_bound + fresh + bound < q / 4);
+        let sanitized = sanitize(&pk, &ct, noise_bound, lambda, &params, seeds.next_seed()).unwrap();
+        assert_eq!(decrypt_full(&sk, &sanitized, &params), m_b);
+        let noise = measure_noise(&sk, &sanitized.0, &sanitized.1, &m_b, &params);
+        let max_noise = noise.iter().map(|e| e.abs()).max().unwrap();
+        assert!(max_noise <= noise_bound + fresh + bound);
+        assert!(max_noise > bound / 2 && max_noise > 1000 * input_noise);
+        assert!(sanitized.0 != ct.0);
+
+        // a larger lambda, or any lambda with the default modulus, is rejected
+        assert!(matches!(sanitize(&pk, &ct, noise_bound, lambda + 1, &params, None), Err(Error::InvalidParameters(_))));
+        let default = Parameters::default();
+        let (pk, _) = keygen(&default, None);
+        let ct = encrypt(&pk.0, &pk.1, &[1], &default, None);
+        assert!(sanitize(&pk, &ct, (2 * default.k * default.n + 1) as i64, 0, &default, None).is_err());
+        assert_eq!(smudging_bound(i64::MAX / 2, 8, &params), None);
+    }
+
+    // Test secret-key encryption, with and without a seed-derived u
+    #[test]
+    pub fn test_secret_key_encryption() {
+        use crate::symmetric::{encrypt_sk, encrypt_sk_seeded, expand_u};
+        use crate::homomorphic::add;
+
+        let params = mul_params();
+        let n = params.n;
+        let (pk, sk) = keygen(&params, None);
+        let m0: Vec<i64> = (0..n as i64).map(|i| i % 3 % 2).collect();
+        let m1: Vec<i64> = (0..n as i64).map(|i| (i + 1) % 2).collect();
+
+        // both modes decrypt with the ordinary decrypt and carry only the noise e
+        let ct0 = encrypt_sk(&sk, &m0, &params, Some(48));
+        assert_eq!(decrypt_full(&sk, &ct0, &params), m0);
+        assert!(measure_noise(&sk, &ct0.0, &ct0.1, &m0, &params).iter().all(|e| e.abs() <= 1));
+        let seeded = encrypt_sk_seeded(&sk, &m1, 7, &params, None);
+        let ct1 = seeded.expand(&params);
+        assert_eq!(ct1.0, expand_u(7, &params));
+        assert_eq!(decrypt_full(&sk, &ct1, &params), m1);
+        // nearby seeds share no polynomial
+        let (u7, u8) = (expand_u(7, &params), expand_u(8, &params));
+        assert!(u7.iter().all(|p| !u8.contains(p)));
+        assert_ne!(ct1.0[0], ct1.0[1]);
+
+        // secret-key ciphertexts mix with public-key ones under the homomorphic operations
+        let sum: Vec<i64> = m0.iter().zip(&m1).map(|(a, b)| (a + b) % 2).collect();
+        assert_eq!(decrypt_full(&sk, &add(&ct0, &ct1, &params), &params), sum);
+        let ct_pk = encrypt(&pk.0, &pk.1, &m1, &params, None);
+        assert_eq!(decrypt_full(&sk, &add(&ct0, &ct_pk, &params), &params), sum);
+        let rlk = relin_keygen(&sk, 1 << 10, &params, None);
+        let bit = encrypt_sk(&sk, &[1], &params, None);
+        let product = mul(&bit, &ct_pk, &rlk, &params);
+        assert_eq!(decrypt_full(&sk, &product, &params), m1);
+
+        // a wrong key does not decrypt
+        let (_, other) = keygen(&params, None);
+        let wrong = measure_noise(&other, &ct0.0, &ct0.1, &m0, &params);
+        assert!(wrong.iter().any(|e| e.abs() > params.q / 8));
+    }
+
+    // Test CRT slot batching with plaintext modulus t
+    #[test]
+    pub fn test_batching() {
+        use crate::batch::{BatchEncoder, encrypt as batch_encrypt, decrypt as batch_decrypt};
+        use crate::homomorphic::{add, mul_with_modulus};
+
+        let params = mul_params();
+        let n = params.n;
+        let t = 97u64;
+        let encoder = BatchEncoder::new(&params, t).unwrap();
+        assert_eq!((encoder.slots(), encoder.plaintext_modulus()), (n, t));
+        let a: Vec<u64> = (0..n as u64).map(|i| (i * 37 + 11) % t).collect();
+        let b: Vec<u64> = (0..n as u64).map(|i| (i * i + 5) % t).collect();
+        let ma = encoder.encode(&a).unwrap();
+        let mb = encoder.encode(&b).unwrap();
+        assert_eq!(encoder.decode(&ma), a);
+        assert!(ma.iter().all(|&c| (0..t as i64).contains(&c)));
+        // a constant in every slot is a constant polynomial
+        let constant = encoder.encode(&vec![9; n]).unwrap();
+        assert_eq!(constant[0], 9);
+        assert!(constant[1..].iter().all(|&c| c == 0));
+
+        // the negacyclic product of the plaintexts mod t multiplies the slots
+        let ti = t as i64;
+        let mut product = vec![0i64; n];
+        for (i, &x) in ma.iter().enumerate() {
+            for (j, &y) in mb.iter().enumerate() {
+                let sign = if i + j < n { 1 } else { -1 };
+                product[(i + j) % n] = (product[(i + j) % n] + sign * x * y).rem_euclid(ti);
+            }
+        }
+        let slot_product: Vec<u64> = a.iter().zip(&b).map(|(x, y)| x * y % t).collect();
+        let slot_sum: Vec<u64> = a.iter().zip(&b).map(|(x, y)| (x + y) % t).collect();
+        assert_eq!(encoder.decode(&product), slot_product);
+
+        // encrypted add and multiply act element-wise on the slots
+        let (pk, sk) = keygen(&params, None);
+        let rlk = relin_keygen(&sk, 1 << 10, &params, None);
+        let ct_a = batch_encrypt(&pk, &ma, ti, &params, None).unwrap();
+        let ct_b = batch_encrypt(&pk, &mb, ti, &params, None).unwrap();
+        assert_eq!(batch_decrypt(&sk, &ct_a, ti, &params), ma);
+        assert_eq!(encoder.decode(&batch_decrypt(&sk, &add(&ct_a, &ct_b, &params), ti, &params)), slot_sum);
+        let ct_ab = mul_with_modulus(&ct_a, &ct_b, ti, &rlk, &params);
+        assert_eq!(encoder.decode(&batch_decrypt(&sk, &ct_ab, ti, &params)), slot_product);
+
+        // with t = 2 the scheme is the binary one
+        let m_b: Vec<i64> = (0..n as i64).map(|i| i % 3 % 2).collect();
+        let ct = encrypt(&pk.0, &pk.1, &m_b, &params, None);
+        assert_eq!(batch_decrypt(&sk, &ct, 2, &params), m_b);
+
+        // t must be a prime below q with t = 1 mod 2n, and slots must fit
+        assert!(BatchEncoder::new(&params, 101).is_err());
+        assert!(BatchEncoder::new(&params, 65).is_err());
+        assert!(BatchEncoder::new(&Parameters::preset("toy").unwrap(), 193).is_err());
+        assert!(encoder.encode(&[t]).is_err());
+        assert!(encoder.encode(&vec![0; n + 1]).is_err());
+        assert!(batch_encrypt(&pk, &ma, 1, &params, None).is_err());
+    }
+
+    // Test Galois automorphisms, slot rotations and the sum over all slots
+    #[test]
+    pub fn test_galois_rotations() {
+        use polynomial_ring::Polynomial;
+        use crate::batch::{BatchEncoder, encrypt as batch_encrypt, decrypt as batch_decrypt};
+        use crate::galois::{automorphism, galois_keygen, apply_galois, rotate_rows, rotation_element, row_swap_element, sum_elements, sum_all_slots};
+        use crate::utils::mul_poly;
+
+        let params = mul_params();
+        let (n, q, f, omega) = (params.n, params.q, &params.f, params.omega);
+        let half = n / 2;
+
+        // automorphisms are ring homomorphisms that compose multiplicatively
+        let a = Polynomial::new((0..n as i64).map(|i| i * 7 - 40).collect());
+        let b = Polynomial::new((0..n as i64).map(|i| 3 - i * i).collect());
+        for g in [3, 5, 2 * n - 1] {
+            let product = mul_poly(&a, &b, q, f, omega);
+            let mapped = mul_poly(&automorphism(&a, g, &params), &automorphism(&b, g, &params), q, f, omega);
+            assert_eq!(automorphism(&product, g, &params), mapped);
+            assert_eq!(automorphism(&automorphism(&a, g, &params), 3, &params), automorphism(&a, g * 3, &params));
+        }
+        assert_eq!(automorphism(&a, 1, &params), a);
+
+        let (pk, sk) = keygen(&params, None);
+        let mut elements = sum_elements(&params);
+        elements.extend([rotation_element(3, &params), rotation_element(-1, &params)]);
+        let gk = galois_keygen(&sk, &elements, 1 << 10, &params, None).unwrap();
+        assert_eq!(rotation_element(half as isize, &params), 1);
+        assert_eq!(rotation_element(-1, &params), rotation_element(half as isize - 1, &params));
+
+        // rotations and the row swap act on the slots as in the plaintext
+        let t = 97;
+        let encoder = BatchEncoder::new(&params, t as u64).unwrap();
+        let slots: Vec<u64> = (0..n as u64).map(|i| (i * 13 + 4) % t as u64).collect();
+        let ct = batch_encrypt(&pk, &encoder.encode(&slots).unwrap(), t, &params, None).unwrap();
+        let rotated_plain = |steps: usize| -> Vec<u64> {
+            (0..n).map(|i| slots[i / half * half + (i % half + steps) % half]).collect()
+        };
+        let decode = |ct: &(Vec<Polynomial<i64>>, Polynomial<i64>)| encoder.decode(&batch_decrypt(&sk, ct, t, &params));
+        assert_eq!(decode(&rotate_rows(&ct, 1, &gk, &params).unwrap()), rotated_plain(1));
+        assert_eq!(decode(&rotate_rows(&ct, 3, &gk, &params).unwrap()), rotated_plain(3));
+        assert_eq!(decode(&rotate_rows(&ct, -1, &gk, &params).unwrap()), rotated_plain(half - 1));
+        let swapped: Vec<u64> = slots[half..].iter().chain(&slots[..half]).copied().collect();
+        assert_eq!(decode(&apply_galois(&ct, row_swap_element(&params), &gk, &params).unwrap()), swapped);
+        let total = slots.iter().sum::<u64>() % t as u64;
+        assert_eq!(decode(&sum_all_slots(&ct, &gk, &params).unwrap()), vec![total; n]);
+
+        // on a coefficient encoding the same sum is the trace, n times the constant coefficient
+        let coeffs: Vec<i64> = (0..n as i64).map(|i| (i * 11 + 3) % t).collect();
+        let ct_coeffs = batch_encrypt(&pk, &coeffs, t, &params, None).unwrap();
+        let trace = batch_decrypt(&sk, &sum_all_slots(&ct_coeffs, &gk, &params).unwrap(), t, &params);
+        assert_eq!(trace[0], n as i64 * coeffs[0] % t);
+        assert!(trace[1..].iter().all(|&c| c == 0));
+
+        // on binary coefficient messages the automorphism permutes the bits up to sign, which vanishes mod 2
+        let m_b: Vec<i64> = (0..n as i64).map(|i| (i * 5 + 2) % 3 % 2).collect();
+        let ct = encrypt(&pk.0, &pk.1, &m_b, &params, None);
+        let expected: Vec<i64> = automorphism(&Polynomial::new(m_b.clone()), 3, &params).coeffs().iter().map(|c| c.rem_euclid(2)).collect();
+        assert_eq!(decrypt_full(&sk, &rotate_rows(&ct, 0, &gk, &params).unwrap(), &params), m_b);
+        let gk3 = galois_keygen(&sk, &[3], 1 << 10, &params, None).unwrap();
+        let mut expected = expected;
+        expected.resize(n, 0);
+        assert_eq!(decrypt_full(&sk, &apply_galois(&ct, 3, &gk3, &params).unwrap(), &params), expected);
+
+        // even elements and missing keys are rejected
+        assert!(galois_keygen(&sk, &[2], 1 << 10, &params, None).is_err());
+        assert!(matches!(apply_galois(&ct, 7, &gk, &params), Err(Error::InvalidParameters(_))));
+    }
+
+    // Strategies for the property tests below
+    // Every parameter set leaves enough headroom that a sum of two ciphertexts decrypts with overwhelming probability
+    fn params_strategy() -> impl Strategy<Value = Parameters> {
+        (prop_oneof![Just((16, 193)), Just((32, 12289)), Just((64, 12289)), Just((128, 7681))], 1..=2usize)
+            .prop_map(|((n, q), k)| Parameters::builder().n(n).q(q).k(k).build().unwrap())
+    }
+
+    fn poly_strategy(params: &Parameters) -> impl Strategy<Value = polynomial_ring::Polynomial<i64>> {
+        let (n, half_q) = (params.n(), params.q() / 2);
+        proptest::collection::vec(-half_q..=half_q, 0..=n).prop_map(polynomial_ring::Polynomial::new)
+    }
+
+    fn vec_strategy(params: &Parameters, len: usize) -> impl Strategy<Value = Vec<polynomial_ring::Polynomial<i64>>> {
+        proptest::collection::vec(poly_strategy(params), len)
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(64))]
+
+        // Ring arithmetic: commutativity, distributivity, NTT versus schoolbook, and transpose
+        #[test]
+        fn prop_ring_arithmetic(
+            (params, a, b, c, m) in params_strategy().prop_flat_map(|params| {
+                let k = params.k();
+                (
+                    Just(params.clone()),
+                    vec_strategy(&params, k),
+                    vec_strategy(&params, k),
+                    vec_strategy(&params, k),
+                    proptest::collection::vec(vec_strategy(&params, k), 1..4),
+                )
+            })
+        ) {
+            use ring_lwe::utils::polymul;
+            use crate::utils::{mul_poly, mul_vec_simple, mul_mat_vec_simple, transpose};
+
+            let (q, f, omega) = (params.q(), params.f(), params.omega());
+            prop_assert_eq!(add_vec(&a, &b, q, f), add_vec(&b, &a, q, f));
+            prop_assert_eq!(mul_vec_simple(&a, &b, q, f, omega), mul_vec_simple(&b, &a, q, f, omega));
+            prop_assert_eq!(
+                mul_vec_simple(&a, &add_vec(&b, &c, q, f), q, f, omega),
+                polyadd(&mul_vec_simple(&a, &b, q, f, omega), &mul_vec_simple(&a, &c, q, f, omega), q, f)
+            );
+            for (x, y) in a.iter().zip(b.iter()) {
+                prop_assert_eq!(mul_poly(x, y, q, f, omega), polymul(x, y, q, f));
+            }
+            prop_assert_eq!(transpose(&transpose(&m)), m.clone());
+            let product = mul_mat_vec_simple(&m, &a, q, f, omega);
+            prop_assert_eq!(product.len(), m.len());
+            for (row, p) in m.iter().zip(product.iter()) {
+                prop_assert_eq!(p, &mul_vec_simple(row, &a, q, f, omega));
+            }
+        }
+
+        // Encrypt/decrypt round trips and homomorphic addition over random messages and parameter sets
+        #[test]
+        fn prop_scheme_round_trips(
+            (params, m0, m1) in params_strategy().prop_flat_map(|params| {
+                let n = params.n();
+                (
+                    Just(params),
+                    proptest::collection::vec(0..2i64, 0..=n),
+                    proptest::collection::vec(0..2i64, 0..=n),
+                )
+            }),
+            seed in any::<u64>(),
+            message in "[ -~]{0,24}",
+        ) {
+            use crate::homomorphic::add;
+
+            let n = params.n();
+            let padded = |m: &[i64]| { let mut m = m.to_vec(); m.resize(n, 0); m };
+            let (pk, sk) = keygen(&params, Some(seed));
+            let ct0 = encrypt(&pk.0, &pk.1, &m0, &params, Some(seed.wrapping_add(1)));
+            let ct1 = encrypt(&pk.0, &pk.1, &m1, &params, Some(seed.wrapping_add(2)));
+            prop_assert_eq!(padded(&decrypt(&sk, &ct0.0, &ct0.1, &params)), padded(&m0));
+            prop_assert_eq!(padded(&decrypt(&sk, &ct1.0, &ct1.1, &params)), padded(&m1));
+
+            let sum = add(&ct0, &ct1, &params);
+            let expected: Vec<i64> = padded(&m0).iter().zip(padded(&m1).iter()).map(|(x, y)| (x + y) % 2).collect();
+            prop_assert_eq!(padded(&decrypt(&sk, &sum.0, &sum.1, &params)), expected);
+
+            let keys = keygen_string(&params, Some(seed));
+            let ct_string = encrypt_string(keys.get("public").unwrap(), &message, &params, Some(seed)).unwrap();
+            prop_assert_eq!(decrypt_string(keys.get("secret").unwrap(), &ct_string, &params).unwrap(), message);
+        }
+    }
+}
@@ -0,0 +1,219 @@
+use polynomial_ring::Polynomial;
+use ring_lwe::utils::{polyadd, polysub, gen_uniform_poly};
+use ntt::mod_exp;
+use crate::utils::{Parameters, PolyVec, add_vec, mul_vec_simple, scalar_mul, gen_bounded_vector, SeedStream};
+use crate::decrypt::decode;
+use crate::types::{check_vec, check_poly};
+use crate::error::Error;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// One party's Shamir share of the secret key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyShare {
+    /// Evaluation point of the share, from 1 to N
+    pub index: i64,
+    /// Evaluation of the sharing polynomial at `index`, coefficient by coefficient
+    pub sk: PolyVec,
+}
+
+/// One party's contribution to a threshold decryption
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(from = "RawPartialDecryption", into = "RawPartialDecryption"))]
+pub struct PartialDecryption {
+    /// Evaluation point of the party's share
+    pub index: i64,
+    /// lambda_i * sk_i * u + smudging noise
+    pub d: Polynomial<i64>,
+}
+
+/// Split a secret key into t-of-N Shamir shares over Z_q
+/// Every coefficient of `sk` is the constant term of its own random polynomial of degree t - 1,
+/// and party i receives the evaluations at x = i
+/// # Arguments
+/// * `sk` - secret key from `keygen::keygen`
+/// * `threshold` - number of shares t needed to decrypt
+/// * `parties` - number of shares N
+/// * `params` - Parameters for the module-LWE cryptosystem
+/// * `seed` - random seed
+/// # Returns
+/// * `shares` - N key shares with indices 1..=N, or an error unless 1 <= t <= N < q
+/// # Example
+/// ```
+/// let params = module_lwe::utils::Parameters::preset("toy").unwrap();
+/// let (_, sk) = module_lwe::keygen::keygen(&params, None);
+/// let shares = module_lwe::threshold::share_secret_key(&sk, 2, 3, &params, None).unwrap();
+/// assert_eq!(module_lwe::threshold::reconstruct_secret_key(&shares[1..], &params).unwrap(), sk);
+/// ```
+pub fn share_secret_key(
+    sk: &[Polynomial<i64>],
+    threshold: usize,
+    parties: usize,
+    params: &Parameters,
+    seed: Option<u64>
+) -> Result<Vec<KeyShare>, Error> {
+    let (n, q, k, f) = (params.n, params.q, params.k, &params.f);
+    if threshold == 0 || threshold > parties || parties as i64 >= q {
+        return Err(Error::InvalidParameters(format!("need 1 <= t <= N < q, got t = {}, N = {}", threshold, parties)));
+    }
+    check_vec(sk, params)?;
+    let mut seeds = SeedStream::new(seed);
+    // sharing polynomial sk + r_1 x + ... + r_{t-1} x^{t-1}, stored from the constant term up
+    let mut coefficients = vec![sk.to_vec()];
+    for _ in 1..threshold {
+        coefficients.push((0..k).map(|_| gen_uniform_poly(n, q, seeds.next_seed())).collect());
+    }
+    let shares = (1..=parties as i64)
+        .map(|index| {
+            // Horner evaluation at x = index
+            let sk = coefficients.iter().rev().fold(vec![Polynomial::new(vec![]); k], |acc, c| {
+                let acc: PolyVec = acc.iter().map(|p| scalar_mul(p, index, q)).collect();
+                add_vec(&acc, c, q, f)
+            });
+            KeyShare { index, sk }
+        })
+        .collect();
+    Ok(shares)
+}
+
+/// Lagrange coefficients at x = 0 for a set of distinct evaluation points
+/// # Arguments
+/// * `indices` - evaluation points of the participating shares
+/// * `q` - prime modulus
+/// # Returns
+/// * `lambdas` - lambda_i with sum_i lambda_i * p(x_i) = p(0) mod q for every p of degree below the set size,
+///   or an error if the points are not distinct and nonzero mod q
+pub fn lagrange_coefficients(indices: &[i64], q: i64) -> Result<Vec<i64>, Error> {
+    for (i, &x) in indices.iter().enumerate() {
+        if x.rem_euclid(q) == 0 || indices[..i].iter().any(|&y| (y - x).rem_euclid(q) == 0) {
+            return Err(Error::InvalidParameters(format!("share index {} is zero or repeated", x)));
+        }
+    }
+    let lambdas = indices.iter()
+        .map(|&xi| {
+            let (num, den) = indices.iter()
+                .filter(|&&xj| xj != xi)
+                .fold((1i64, 1i64), |(num, den), &xj| {
+                    (num * xj.rem_euclid(q) % q, den * (xj - xi).rem_euclid(q) % q)
+                });
+            num * mod_exp(den, q - 2, q) % q
+        })
+        .collect();
+    Ok(lambdas)
+}
+
+/// Reconstruct the secret key from at least t shares
+/// # Arguments
+/// * `shares` - key shares with distinct indices
+/// * `params` - Parameters for the module-LWE cryptosystem
+/// # Returns
+/// * `sk` - the shared secret key if there are at least t shares; fewer give an unrelated key
+pub fn reconstruct_secret_key(shares: &[KeyShare], params: &Parameters) -> Result<PolyVec, Error> {
+    let (q, k, f) = (params.q, params.k, &params.f);
+    let indices: Vec<i64> = shares.iter().map(|s| s.index).collect();
+    let lambdas = lagrange_coefficients(&indices, q)?;
+    let mut sk = vec![Polynomial::new(vec![]); k];
+    for (share, lambda) in shares.iter().zip(lambdas) {
+        check_vec(&share.sk, params)?;
+        let scaled: PolyVec = share.sk.iter().map(|p| scalar_mul(p, lambda, q)).collect();
+        sk = add_vec(&sk, &scaled, q, f);
+    }
+    Ok(sk)
+}
+
+/// Compute one party's partial decryption for a given set of participants
+/// The party weights its share by its Lagrange coefficient for `participants`, so the partial decryptions of the
+/// set add up to `sk*u`, and adds noise uniform in [-smudging, smudging] to hide its share in the output.
+/// The combined noise of t parties must stay below q/4 together with the ciphertext noise.
+/// # Arguments
+/// * `share` - the party's key share
+/// * `participants` - indices of the t or more parties taking part, including this one
+/// * `u` - ciphertext vector
+/// * `smudging` - bound on the smudging noise
+/// * `params` - Parameters for the module-LWE cryptosystem
+/// * `seed` - random seed
+/// # Returns
+/// * `partial` - partial decryption, or an error if the party is not among the participants or `u` is malformed
+pub fn partial_decrypt(
+    share: &KeyShare,
+    participants: &[i64],
+    u: &[Polynomial<i64>],
+    smudging: i64,
+    params: &Parameters,
+    seed: Option<u64>
+) -> Result<PartialDecryption, Error> {
+    let (n, q, f, omega) = (params.n, params.q, &params.f, params.omega);
+    check_vec(u, params)?;
+    check_vec(&share.sk, params)?;
+    let position = participants.iter()
+        .position(|&i| i == share.index)
+        .ok_or_else(|| Error::InvalidParameters(format!("party {} is not a participant", share.index)))?;
+    let lambda = lagrange_coefficients(participants, q)?[position];
+    let weighted: PolyVec = share.sk.iter().map(|p| scalar_mul(p, lambda, q)).collect();
+    let noise = gen_bounded_vector(n, 1, smudging.max(0), seed).remove(0);
+    let d = polyadd(&mul_vec_simple(&weighted, u, q, f, omega), &noise, q, f);
+    Ok(PartialDecryption { index: share.index, d })
+}
+
+/// Combine partial decryptions into the plaintext
+/// # Arguments
+/// * `partials` - partial decryptions of every participant, computed for the same set
+/// * `v` - ciphertext polynomial
+/// * `params` - Parameters for the module-LWE cryptosystem
+/// # Returns
+/// * `decrypted_coeffs` - plaintext vector decoded from `v - sum_i d_i`
+/// # Example
+/// ```
+/// use module_lwe::threshold::{share_secret_key, partial_decrypt, combine};
+/// let params = module_lwe::utils::Parameters::default();
+/// let (pk, sk) = module_lwe::keygen::keygen(&params, None);
+/// let mut m_b = vec![1, 0, 1, 1];
+/// m_b.resize(params.n(), 0);
+/// let (u, v) = module_lwe::encrypt::encrypt(&pk.0, &pk.1, &m_b, &params, None);
+/// let shares = share_secret_key(&sk, 2, 3, &params, None).unwrap();
+/// let participants = [1, 3];
+/// let partials: Vec<_> = [&shares[0], &shares[2]].iter()
+///     .map(|share| partial_decrypt(share, &participants, &u, 100, &params, None).unwrap())
+///     .collect();
+/// assert_eq!(combine(&partials, &v, &params).unwrap(), m_b);
+/// ```
+pub fn combine(partials: &[PartialDecryption], v: &Polynomial<i64>, params: &Parameters) -> Result<Vec<i64>, Error> {
+    let (n, q, f) = (params.n, params.q, &params.f);
+    if partials.is_empty() {
+        return Err(Error::InvalidParameters(String::from("no partial decryptions to combine")));
+    }
+    let indices: Vec<i64> = partials.iter().map(|p| p.index).collect();
+    lagrange_coefficients(&indices, q)?;
+    check_poly(v, params)?;
+    let mut phase = v.clone();
+    for partial in partials {
+        check_poly(&partial.d, params)?;
+        phase = polysub(&phase, &partial.d, q, f);
+    }
+    let mut decrypted_coeffs = decode(&phase, q);
+    decrypted_coeffs.resize(n, 0);
+    Ok(decrypted_coeffs)
+}
+
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+struct RawPartialDecryption {
+    index: i64,
+    d: Vec<i64>,
+}
+
+#[cfg(feature = "serde")]
+impl From<PartialDecryption> for RawPartialDecryption {
+    fn from(partial: PartialDecryption) -> Self {
+        RawPartialDecryption { index: partial.index, d: partial.d.coeffs().to_vec() }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<RawPartialDecryption> for PartialDecryption {
+    fn from(raw: RawPartialDecryption) -> Self {
+        // coefficients are range-checked by `combine`, which needs the parameters
+        PartialDecryption { index: raw.index, d: Polynomial::new(raw.d) }
+    }
+}
@@ -0,0 +1,276 @@
+use polynomial_ring::Polynomial;
+use ring_lwe::utils::mod_coeffs;
+use crate::utils::{Parameters, PolyVec, PolyMatrix, compress};
+use crate::error::Error;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Public key (a, t) together with the parameters it was generated for
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "RawPublicKey", into = "RawPublicKey"))]
+pub struct PublicKey {
+    /// Parameters for the module-LWE cryptosystem
+    pub params: Parameters,
+    /// Uniform public matrix
+    pub a: PolyMatrix,
+    /// t = a*sk + e
+    pub t: PolyVec,
+}
+
+/// Secret key together with the parameters it was generated for
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "RawSecretKey", into = "RawSecretKey"))]
+pub struct SecretKey {
+    /// Parameters for the module-LWE cryptosystem
+    pub params: Parameters,
+    /// Small secret vector
+    pub s: PolyVec,
+}
+
+/// Ciphertext (u, v) of a single message block together with its parameters
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "RawCiphertext", into = "RawCiphertext"))]
+pub struct Ciphertext {
+    /// Parameters for the module-LWE cryptosystem
+    pub params: Parameters,
+    /// Ciphertext vector
+    pub u: PolyVec,
+    /// Ciphertext polynomial
+    pub v: Polynomial<i64>,
+}
+
+impl PublicKey {
+    /// Wrap the public key returned by `keygen::keygen`
+    pub fn new(params: &Parameters, pk: (PolyMatrix, PolyVec)) -> Self {
+        PublicKey { params: params.clone(), a: pk.0, t: pk.1 }
+    }
+
+    /// Check that the key has the shape and coefficient range required by its parameters
+    pub fn validate(&self) -> Result<(), Error> {
+        check_len("a", self.a.len(), self.params.k)?;
+        for row in self.a.iter() {
+            check_vec(row, &self.params)?;
+        }
+        check_vec(&self.t, &self.params)
+    }
+
+    /// Encode the public key in the base64 format of `keygen::keygen_string`
+    pub fn to_base64(&self) -> String {
+        let coeffs: Vec<i64> = self.a.iter().flatten().chain(self.t.iter())
+            .flat_map(|p| padded(p, self.params.n))
+            .collect();
+        compress(&coeffs)
+    }
+}
+
+impl SecretKey {
+    /// Wrap the secret key returned by `keygen::keygen`
+    pub fn new(params: &Parameters, sk: PolyVec) -> Self {
+        SecretKey { params: params.clone(), s: sk }
+    }
+
+    /// Check that the key has the shape and coefficient range required by its parameters
+    pub fn validate(&self) -> Result<(), Error> {
+        check_vec(&self.s, &self.params)
+    }
+
+    /// Encode the secret key in the base64 format of `keygen::keygen_string`
+    pub fn to_base64(&self) -> String {
+        let coeffs: Vec<i64> = self.s.iter().flat_map(|p| padded(p, self.params.n)).collect();
+        compress(&coeffs)
+    }
+}
+
+impl Ciphertext {
+    /// Wrap the ciphertext returned by `encrypt::encrypt`
+    pub fn new(params: &Parameters, ct: (PolyVec, Polynomial<i64>)) -> Self {
+        Ciphertext { params: params.clone(), u: ct.0, v: ct.1 }
+    }
+
+    /// Check that the ciphertext has the shape and coefficient range required by its parameters
+    pub fn validate(&self) -> Result<(), Error> {
+        check_vec(&self.u, &self.params)?;
+        check_poly(&self.v, &self.params)
+    }
+}
+
+/// Coefficients of a polynomial, resized to include leading zeros up to size `n`
+fn padded(p: &Polynomial<i64>, n: usize) -> Vec<i64> {
+    let mut coeffs = p.coeffs().to_vec();
+    coeffs.resize(n, 0);
+    coeffs
+}
+
+pub(crate) fn check_len(name: &str, len: usize, expected: usize) -> Result<(), Error> {
+    if len != expected {
+        return Err(Error::Decode(format!("{} has length {}, expected {}", name, len, expected)));
+    }
+    Ok(())
+}
+
+pub(crate) fn check_poly(p: &Polynomial<i64>, params: &Parameters) -> Result<(), Error> {
+    let (n, q) = (params.n, params.q);
+    if p.coeffs().len() > n {
+        return Err(Error::Decode(format!("polynomial has {} coefficients, expected at most {}", p.coeffs().len(), n)));
+    }
+    if let Some(c) = p.coeffs().iter().find(|c| c.unsigned_abs() >= q as u64) {
+        return Err(Error::Decode(format!("coefficient {} is out of range for q = {}", c, q)));
+    }
+    Ok(())
+}
+
+pub(crate) fn check_vec(v: &[Polynomial<i64>], params: &Parameters) -> Result<(), Error> {
+    check_len("polynomial vector", v.len(), params.k)?;
+    v.iter().try_for_each(|p| check_poly(p, params))
+}
+
+/// Coefficients of a polynomial in [0, q), padded to length n
+pub(crate) fn to_canonical(p: &Polynomial<i64>, params: &Parameters) -> Vec<i64> {
+    let mut coeffs: Vec<i64> = p.coeffs().iter().map(|c| c.rem_euclid(params.q)).collect();
+    coeffs.resize(params.n, 0);
+    coeffs
+}
+
+/// Range-checked inverse of `to_canonical`, returning the centered polynomial
+pub(crate) fn from_canonical(coeffs: Vec<i64>, params: &Parameters) -> Result<Polynomial<i64>, Error> {
+    check_len("polynomial", coeffs.len(), params.n)?;
+    if let Some(c) = coeffs.iter().find(|&&c| c < 0 || c >= params.q) {
+        return Err(Error::Decode(format!("coefficient {} is out of range for q = {}", c, params.q)));
+    }
+    Ok(mod_coeffs(Polynomial::new(coeffs), params.q))
+}
+
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+struct RawParameters {
+    n: usize,
+    q: i64,
+    k: usize,
+}
+
+#[cfg(feature = "serde")]
+impl From<Parameters> for RawParameters {
+    fn from(params: Parameters) -> Self {
+        RawParameters { n: params.n, q: params.q, k: params.k }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<RawParameters> for Parameters {
+    type Error = Error;
+
+    fn try_from(raw: RawParameters) -> Result<Self, Error> {
+        Parameters::builder().n(raw.n).q(raw.q).k(raw.k).build()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for Parameters {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        RawParameters::from(self.clone()).serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for Parameters {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Parameters::try_from(RawParameters::deserialize(deserializer)?).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+struct RawPublicKey {
+    params: Parameters,
+    a: Vec<Vec<Vec<i64>>>,
+    t: Vec<Vec<i64>>,
+}
+
+#[cfg(feature = "serde")]
+impl From<PublicKey> for RawPublicKey {
+    fn from(pk: PublicKey) -> Self {
+        let params = &pk.params;
+        let a = pk.a.iter().map(|row| row.iter().map(|p| to_canonical(p, params)).collect()).collect();
+        let t = pk.t.iter().map(|p| to_canonical(p, params)).collect();
+        RawPublicKey { params: pk.params.clone(), a, t }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<RawPublicKey> for PublicKey {
+    type Error = Error;
+
+    fn try_from(raw: RawPublicKey) -> Result<Self, Error> {
+        let params = raw.params;
+        check_len("a", raw.a.len(), params.k)?;
+        let a = raw.a.into_iter()
+            .map(|row| {
+                check_len("row of a", row.len(), params.k)?;
+                row.into_iter().map(|c| from_canonical(c, &params)).collect()
+            })
+            .collect::<Result<PolyMatrix, Error>>()?;
+        check_len("t", raw.t.len(), params.k)?;
+        let t = raw.t.into_iter().map(|c| from_canonical(c, &params)).collect::<Result<PolyVec, Error>>()?;
+        Ok(PublicKey { params, a, t })
+    }
+}
+
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+struct RawSecretKey {
+    params: Parameters,
+    s: Vec<Vec<i64>>,
+}
+
+#[cfg(feature = "serde")]
+impl From<SecretKey> for RawSecretKey {
+    fn from(sk: SecretKey) -> Self {
+        let s = sk.s.iter().map(|p| to_canonical(p, &sk.params)).collect();
+        RawSecretKey { params: sk.params, s }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<RawSecretKey> for SecretKey {
+    type Error = Error;
+
+    fn try_from(raw: RawSecretKey) -> Result<Self, Error> {
+        let params = raw.params;
+        check_len("s", raw.s.len(), params.k)?;
+        let s = raw.s.into_iter().map(|c| from_canonical(c, &params)).collect::<Result<PolyVec, Error>>()?;
+        Ok(SecretKey { params, s })
+    }
+}
+
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+struct RawCiphertext {
+    params: Parameters,
+    u: Vec<Vec<i64>>,
+    v: Vec<i64>,
+}
+
+#[cfg(feature = "serde")]
+impl From<Ciphertext> for RawCiphertext {
+    fn from(ct: Ciphertext) -> Self {
+        let u = ct.u.iter().map(|p| to_canonical(p, &ct.params)).collect();
+        let v = to_canonical(&ct.v, &ct.params);
+        RawCiphertext { params: ct.params, u, v }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<RawCiphertext> for Ciphertext {
+    type Error = Error;
+
+    fn try_from(raw: RawCiphertext) -> Result<Self, Error> {
+        let params = raw.params;
+        check_len("u", raw.u.len(), params.k)?;
+        let u = raw.u.into_iter().map(|c| from_canonical(c, &params)).collect::<Result<PolyVec, Error>>()?;
+        let v = from_canonical(raw.v, &params)?;
+        Ok(Ciphertext { params, u, v })
+    }
+}
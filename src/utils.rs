@@ -2,41 +2,187 @@ use polynomial_ring::Polynomial;
 use rand_distr::{Uniform, Distribution};
 use rand::SeedableRng;
 use rand::rngs::StdRng;
-use ring_lwe::utils::{polyadd, polymul_fast, gen_uniform_poly};
-use ntt::omega;
+use ring_lwe::utils::{polyadd, gen_uniform_poly, mod_coeffs};
+use ntt::{omega, polymul_ntt};
 use base64::{engine::general_purpose, Engine as _};
-use bincode;
+use sha3::Shake256;
+use sha3::digest::{Update, ExtendableOutput, XofReader};
+use crate::error::Error;
 
-#[derive(Debug)]
+/// Vector of polynomials, an element of R_q^k
+pub type PolyVec = Vec<Polynomial<i64>>;
+/// Matrix of polynomials, an element of R_q^{k x k}
+pub type PolyMatrix = Vec<Vec<Polynomial<i64>>>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 /// Default parameters for module-LWE
+/// Fields can only be set through `ParametersBuilder`, which checks the invariants the scheme relies on
 pub struct Parameters {
     /// Degree of the polynomials
-    pub n: usize,
+    pub(crate) n: usize,
     /// Ciphertext modulus
-    pub q: i64,
-    /// Module rank	
-    pub k: usize,
-    /// 2n-th root of unity	
-    pub omega: i64,
+    pub(crate) q: i64,
+    /// Module rank
+    pub(crate) k: usize,
+    /// 2n-th root of unity
+    pub(crate) omega: i64,
     /// Polynomial modulus
-    pub f: Polynomial<i64>,
+    pub(crate) f: Polynomial<i64>,
 }
 
 impl Default for Parameters {
     fn default() -> Self {
-        let n = 512;
-        let q = 12289;
-        let k = 8;
+        ParametersBuilder::new().build().expect("default parameters are valid")
+    }
+}
+
+/// Named parameter sets as (name, n, q, k)
+pub const PRESETS: [(&str, usize, i64, usize); 3] = [
+    ("toy", 16, 193, 2),
+    ("small", 256, 12289, 2),
+    ("default", 512, 12289, 8),
+];
+
+impl Parameters {
+    /// Start building a validated set of parameters
+    pub fn builder() -> ParametersBuilder {
+        ParametersBuilder::new()
+    }
+
+    /// Parameters of a named preset from `PRESETS`
+    /// # Example
+    /// ```
+    /// use module_lwe::utils::Parameters;
+    /// assert_eq!(Parameters::preset("default"), Some(Parameters::default()));
+    /// assert_eq!(Parameters::preset("small").unwrap().n(), 256);
+    /// assert!(Parameters::preset("huge").is_none());
+    /// ```
+    pub fn preset(name: &str) -> Option<Parameters> {
+        PRESETS.iter()
+            .find(|(preset, ..)| *preset == name)
+            .map(|&(_, n, q, k)| Parameters::builder().n(n).q(q).k(k).build().expect("presets are valid"))
+    }
+
+    /// Degree of the polynomials
+    pub fn n(&self) -> usize {
+        self.n
+    }
+
+    /// Ciphertext modulus
+    pub fn q(&self) -> i64 {
+        self.q
+    }
+
+    /// Module rank
+    pub fn k(&self) -> usize {
+        self.k
+    }
+
+    /// 2n-th root of unity
+    pub fn omega(&self) -> i64 {
+        self.omega
+    }
+
+    /// Polynomial modulus x^n + 1
+    pub fn f(&self) -> &Polynomial<i64> {
+        &self.f
+    }
+}
+
+/// Builder for `Parameters`
+/// # Example
+/// ```
+/// let params = module_lwe::utils::Parameters::builder().n(256).q(12289).k(2).build().unwrap();
+/// assert_eq!(params.n(), 256);
+/// assert!(module_lwe::utils::Parameters::builder().n(256).q(3329).build().is_err());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParametersBuilder {
+    n: usize,
+    q: i64,
+    k: usize,
+}
+
+impl Default for ParametersBuilder {
+    fn default() -> Self {
+        ParametersBuilder { n: 512, q: 12289, k: 8 }
+    }
+}
+
+impl ParametersBuilder {
+    /// Builder starting from the default parameters n = 512, q = 12289, k = 8
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the degree of the polynomials
+    pub fn n(mut self, n: usize) -> Self {
+        self.n = n;
+        self
+    }
+
+    /// Set the ciphertext modulus
+    pub fn q(mut self, q: i64) -> Self {
+        self.q = q;
+        self
+    }
+
+    /// Set the module rank
+    pub fn k(mut self, k: usize) -> Self {
+        self.k = k;
+        self
+    }
+
+    /// Validate the parameters and derive the root of unity and polynomial modulus
+    /// # Returns
+    /// * `params` - parameters with n a power of two, q a prime below 2^31 with q = 1 mod 2n, and k >= 1
+    pub fn build(self) -> Result<Parameters, Error> {
+        let (n, q, k) = (self.n, self.q, self.k);
+        if n < 2 || !n.is_power_of_two() {
+            return Err(Error::InvalidParameters(format!("n = {} is not a power of two", n)));
+        }
+        // q = 1 mod 2n needs 2n < q, so this also keeps 2n from overflowing
+        if n >= 1 << 30 {
+            return Err(Error::InvalidParameters(format!("n = {} is too large for q below 2^31", n)));
+        }
+        // the NTT multiplies residues in i64, so q^2 must not overflow
+        if q >= 1 << 31 {
+            return Err(Error::InvalidParameters(format!("q = {} is not below 2^31", q)));
+        }
+        if !is_prime(q) {
+            return Err(Error::InvalidParameters(format!("q = {} is not prime", q)));
+        }
+        if q % (2 * n as i64) != 1 {
+            return Err(Error::InvalidParameters(format!("q = {} is not 1 mod 2n = {}", q, 2 * n)));
+        }
+        if k == 0 {
+            return Err(Error::InvalidParameters(String::from("k must be at least 1")));
+        }
         let omega = omega(q, 2 * n);
         let mut poly_vec = vec![0i64; n + 1];
         poly_vec[0] = 1;
         poly_vec[n] = 1;
         let f = Polynomial::new(poly_vec);
-        Parameters { n, q, k, omega, f }
+        Ok(Parameters { n, q, k, omega, f })
+    }
+}
+
+/// Deterministic primality test by trial division
+pub fn is_prime(q: i64) -> bool {
+    if q < 2 {
+        return false;
     }
+    let mut d = 2;
+    while d * d <= q {
+        if q % d == 0 {
+            return false;
+        }
+        d += 1;
+    }
+    true
 }
 
-pub fn add_vec(v0: &Vec<Polynomial<i64>>, v1: &Vec<Polynomial<i64>>, modulus: i64, poly_mod: &Polynomial<i64>) -> Vec<Polynomial<i64>> {
+pub fn add_vec(v0: &[Polynomial<i64>], v1: &[Polynomial<i64>], modulus: i64, poly_mod: &Polynomial<i64>) -> Vec<Polynomial<i64>> {
     assert_eq!(v0.len(), v1.len());
     v0.iter()
         .zip(v1.iter())
@@ -44,21 +190,56 @@ pub fn add_vec(v0: &Vec<Polynomial<i64>>, v1: &Vec<Polynomial<i64>>, modulus: i6
         .collect()
 }
 
-pub fn mul_vec_simple(v0: &Vec<Polynomial<i64>>, v1: &Vec<Polynomial<i64>>, modulus: i64, poly_mod: &Polynomial<i64>, omega: i64) -> Polynomial<i64> {
+/// Multiply two polynomials in Z_q[x]/(x^n + 1) with an NTT of size 2n
+/// `polymul_fast` sizes its transform from the input lengths, which only agrees with the 2n-th root of unity
+/// when one input has more than n/2 coefficients, so both inputs are folded into the ring dimension and padded here
+/// # Arguments
+/// * `x`, `y` - polynomials of any length
+/// * `modulus` - ciphertext modulus q
+/// * `poly_mod` - polynomial modulus x^n + 1
+/// * `omega` - 2n-th root of unity mod q
+/// # Returns
+/// * `product` - x*y mod (q, x^n + 1) with centered coefficients
+/// # Example
+/// ```
+/// use polynomial_ring::Polynomial;
+/// let params = module_lwe::utils::Parameters::default();
+/// let x = Polynomial::new(vec![0, 1]);
+/// let product = module_lwe::utils::mul_poly(&x, &x, params.q(), params.f(), params.omega());
+/// assert_eq!(product, Polynomial::new(vec![0, 0, 1]));
+/// ```
+pub fn mul_poly(x: &Polynomial<i64>, y: &Polynomial<i64>, modulus: i64, poly_mod: &Polynomial<i64>, omega: i64) -> Polynomial<i64> {
+    let n = poly_mod.coeffs().len() - 1;
+    // reduce modulo x^n + 1 and q, then pad to the transform size
+    let fold = |p: &Polynomial<i64>| -> Vec<i64> {
+        let mut coeffs = vec![0i64; 2 * n];
+        for (i, c) in p.coeffs().iter().enumerate() {
+            let c = c.rem_euclid(modulus);
+            let j = i % n;
+            coeffs[j] = if (i / n).is_multiple_of(2) { (coeffs[j] + c) % modulus } else { (coeffs[j] + modulus - c) % modulus };
+        }
+        coeffs
+    };
+    let product = polymul_ntt(&fold(x), &fold(y), 2 * n, modulus, omega);
+    let coeffs = (0..n).map(|i| product[i] - product[i + n]).collect();
+    mod_coeffs(Polynomial::new(coeffs), modulus)
+}
+
+pub fn mul_vec_simple(v0: &[Polynomial<i64>], v1: &[Polynomial<i64>], modulus: i64, poly_mod: &Polynomial<i64>, omega: i64) -> Polynomial<i64> {
     assert_eq!(v0.len(), v1.len());
     v0.iter()
         .zip(v1.iter())
-        .map(|(a, b)| polymul_fast(a, b, modulus, poly_mod, omega))
+        .map(|(a, b)| mul_poly(a, b, modulus, poly_mod, omega))
         .fold(Polynomial::new(vec![]), |acc, p| polyadd(&acc, &p, modulus, poly_mod))
 }
 
-pub fn mul_mat_vec_simple(m: &Vec<Vec<Polynomial<i64>>>, v: &Vec<Polynomial<i64>>, modulus: i64, poly_mod: &Polynomial<i64>, omega: i64) -> Vec<Polynomial<i64>> {
+pub fn mul_mat_vec_simple(m: &[Vec<Polynomial<i64>>], v: &[Polynomial<i64>], modulus: i64, poly_mod: &Polynomial<i64>, omega: i64) -> Vec<Polynomial<i64>> {
     m.iter()
         .map(|row| mul_vec_simple(row, v, modulus, poly_mod, omega))
         .collect()
 }
 
-pub fn transpose(m: &Vec<Vec<Polynomial<i64>>>) -> Vec<Vec<Polynomial<i64>>> {
+pub fn transpose(m: &[Vec<Polynomial<i64>>]) -> PolyMatrix {
     let rows = m.len();
     let cols = m[0].len();
     let mut result = vec![vec![Polynomial::new(vec![]); rows]; cols];
@@ -70,7 +251,54 @@ pub fn transpose(m: &Vec<Vec<Polynomial<i64>>>) -> Vec<Vec<Polynomial<i64>>> {
     result
 }
 
-pub fn gen_small_vector(size: usize, rank: usize, seed: Option<u64>) -> Vec<Polynomial<i64>> {
+/// Derive the seed of sample `index` from a master seed
+/// Hashes seed || index with SHAKE256, so that the seeds of nearby masters or indices are unrelated
+/// # Arguments
+/// * `seed` - master seed
+/// * `index` - sample index
+/// # Returns
+/// * `derived` - seed for the sample
+pub fn derive_seed(seed: u64, index: u64) -> u64 {
+    let mut hasher = Shake256::default();
+    hasher.update(&seed.to_le_bytes());
+    hasher.update(&index.to_le_bytes());
+    let mut bytes = [0u8; 8];
+    hasher.finalize_xof().read(&mut bytes);
+    u64::from_le_bytes(bytes)
+}
+
+/// Stream of distinct seeds for the samples drawn from one optional seed
+/// An unseeded stream yields `None` throughout, so every sample is drawn from entropy
+/// # Example
+/// ```
+/// use module_lwe::utils::SeedStream;
+/// let mut seeds = SeedStream::new(Some(7));
+/// let (s0, s1) = (seeds.next_seed(), seeds.next_seed());
+/// assert_ne!(s0, s1);
+/// assert_eq!(SeedStream::new(Some(7)).next_seed(), s0);
+/// assert_eq!(SeedStream::new(None).next_seed(), None);
+/// ```
+#[derive(Debug, Clone)]
+pub struct SeedStream {
+    seed: Option<u64>,
+    index: u64,
+}
+
+impl SeedStream {
+    /// Start a stream from an optional master seed
+    pub fn new(seed: Option<u64>) -> Self {
+        SeedStream { seed, index: 0 }
+    }
+
+    /// Seed for the next sample, or `None` if the stream is unseeded
+    pub fn next_seed(&mut self) -> Option<u64> {
+        let index = self.index;
+        self.index += 1;
+        self.seed.map(|s| derive_seed(s, index))
+    }
+}
+
+pub fn gen_small_vector(size: usize, rank: usize, seed: Option<u64>) -> PolyVec {
     let between = Uniform::new(0, 3);
     let mut rng = match seed {
         Some(s) => StdRng::seed_from_u64(s),
@@ -85,22 +313,113 @@ pub fn gen_small_vector(size: usize, rank: usize, seed: Option<u64>) -> Vec<Poly
         .collect()
 }
 
-pub fn gen_uniform_matrix(size: usize, rank: usize, modulus: i64, seed: Option<u64>) -> Vec<Vec<Polynomial<i64>>> {
+/// Generate `rank` polynomials with coefficients drawn uniformly from [-bound, bound]
+pub fn gen_bounded_vector(size: usize, rank: usize, bound: i64, seed: Option<u64>) -> PolyVec {
+    let between = Uniform::new_inclusive(-bound, bound);
+    let mut rng = match seed {
+        Some(s) => StdRng::seed_from_u64(s),
+        None => StdRng::from_entropy(),
+    };
+
+    (0..rank)
+        .map(|_| Polynomial::new((0..size).map(|_| between.sample(&mut rng)).collect()))
+        .collect()
+}
+
+pub fn gen_uniform_matrix(size: usize, rank: usize, modulus: i64, seed: Option<u64>) -> PolyMatrix {
+    let mut seeds = SeedStream::new(seed);
     (0..rank)
         .map(|_| {
             (0..rank)
-                .map(|_| gen_uniform_poly(size, modulus, seed))
+                .map(|_| gen_uniform_poly(size, modulus, seeds.next_seed()))
                 .collect::<Vec<_>>()
         })
         .collect::<Vec<_>>()
 }
 
-pub fn compress(data: &Vec<i64>) -> String {
+pub fn compress(data: &[i64]) -> String {
     let serialized = bincode::serialize(data).expect("Failed to serialize data");
     general_purpose::STANDARD.encode(&serialized)
 }
 
-pub fn decompress(base64_str: &str) -> Vec<i64> {
-    let decoded = general_purpose::STANDARD.decode(base64_str).expect("Failed to decode base64 string");
-    bincode::deserialize(&decoded).expect("Failed to deserialize data")
-}
\ No newline at end of file
+/// Decode a base64 string produced by `compress`, returning an error on malformed input
+pub fn decompress(base64_str: &str) -> Result<Vec<i64>, Error> {
+    let decoded = general_purpose::STANDARD.decode(base64_str).map_err(|e| Error::Decode(e.to_string()))?;
+    bincode::deserialize(&decoded).map_err(|e| Error::Decode(e.to_string()))
+}
+
+/// Multiply every coefficient of a polynomial by a scalar mod `modulus`
+/// # Arguments
+/// * `p` - polynomial
+/// * `scalar` - integer scalar
+/// * `modulus` - coefficient modulus
+/// # Returns
+/// * `product` - scalar * p with centered coefficients
+/// # Example
+/// ```
+/// use polynomial_ring::Polynomial;
+/// let p = Polynomial::new(vec![1, -2, 3]);
+/// assert_eq!(module_lwe::utils::scalar_mul(&p, 5, 17), Polynomial::new(vec![5, 7, -2]));
+/// ```
+pub fn scalar_mul(p: &Polynomial<i64>, scalar: i64, modulus: i64) -> Polynomial<i64> {
+    let coeffs: Vec<i64> = p.coeffs()
+        .iter()
+        .map(|&c| ((c as i128 * scalar as i128).rem_euclid(modulus as i128)) as i64)
+        .collect();
+    mod_coeffs(Polynomial::new(coeffs), modulus)
+}
+
+/// Number of base-`base` digits needed to represent every coefficient mod `modulus`
+/// # Arguments
+/// * `modulus` - coefficient modulus
+/// * `base` - decomposition base
+/// # Returns
+/// * `levels` - smallest l such that base^l >= modulus
+pub fn gadget_levels(modulus: i64, base: i64) -> usize {
+    assert!(base >= 2, "gadget base must be at least 2");
+    let mut levels = 0;
+    let mut power: i128 = 1;
+    while power < modulus as i128 {
+        power *= base as i128;
+        levels += 1;
+    }
+    levels
+}
+
+/// Balanced gadget decomposition of a polynomial
+/// # Arguments
+/// * `p` - polynomial in R_q
+/// * `base` - decomposition base
+/// * `levels` - number of digits
+/// * `modulus` - coefficient modulus
+/// # Returns
+/// * `digits` - polynomials d_0, ..., d_{levels-1} with small coefficients such that sum(d_l * base^l) = p mod q
+/// # Example
+/// ```
+/// let p = polynomial_ring::Polynomial::new(vec![12288, 5, -77]);
+/// let digits = module_lwe::utils::gadget_decompose(&p, 16, 4, 12289);
+/// let recomposed = digits.iter().rev().fold(polynomial_ring::Polynomial::new(vec![]), |acc, d| {
+///     &(&acc * &polynomial_ring::Polynomial::new(vec![16])) + d
+/// });
+/// assert_eq!(recomposed, polynomial_ring::Polynomial::new(vec![-1, 5, -77]));
+/// ```
+pub fn gadget_decompose(p: &Polynomial<i64>, base: i64, levels: usize, modulus: i64) -> PolyVec {
+    let mut digits = vec![vec![0i64; p.coeffs().len()]; levels];
+    for (i, &c) in p.coeffs().iter().enumerate() {
+        // centered representative of the coefficient
+        let mut x = c.rem_euclid(modulus);
+        if x > modulus / 2 {
+            x -= modulus;
+        }
+        for (l, digit) in digits.iter_mut().enumerate() {
+            // the top digit absorbs whatever carry is left
+            let mut d = if l + 1 == levels { x } else { x.rem_euclid(base) };
+            if l + 1 < levels && d >= base - base / 2 {
+                d -= base;
+            }
+            digit[i] = d;
+            x = (x - d) / base;
+        }
+    }
+    digits.into_iter().map(Polynomial::new).collect()
+}